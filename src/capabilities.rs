@@ -0,0 +1,92 @@
+/// Linux capability names without `CAP_`, indexed by capability number.
+pub(crate) const CAPABILITY_NAMES: &[&str] = &[
+    "CHOWN",
+    "DAC_OVERRIDE",
+    "DAC_READ_SEARCH",
+    "FOWNER",
+    "FSETID",
+    "KILL",
+    "SETGID",
+    "SETUID",
+    "SETPCAP",
+    "LINUX_IMMUTABLE",
+    "NET_BIND_SERVICE",
+    "NET_BROADCAST",
+    "NET_ADMIN",
+    "NET_RAW",
+    "IPC_LOCK",
+    "IPC_OWNER",
+    "SYS_MODULE",
+    "SYS_RAWIO",
+    "SYS_CHROOT",
+    "SYS_PTRACE",
+    "SYS_PACCT",
+    "SYS_ADMIN",
+    "SYS_BOOT",
+    "SYS_NICE",
+    "SYS_RESOURCE",
+    "SYS_TIME",
+    "SYS_TTY_CONFIG",
+    "MKNOD",
+    "LEASE",
+    "AUDIT_WRITE",
+    "AUDIT_CONTROL",
+    "SETFCAP",
+    "MAC_OVERRIDE",
+    "MAC_ADMIN",
+    "SYSLOG",
+    "WAKE_ALARM",
+    "BLOCK_SUSPEND",
+    "AUDIT_READ",
+    "PERFMON",
+    "BPF",
+    "CHECKPOINT_RESTORE",
+];
+
+/// Accepted by `--drop-caps` and `--keep-caps` in place of a list.
+pub(crate) const ALL_CAPABILITIES: &str = "ALL";
+
+/// Normalises a capability given with or without `CAP_`, in any case, into
+/// its `CAP_`-prefixed name; `ALL` is passed through.
+pub(crate) fn canonical_capability_name(raw: &str) -> Option<String> {
+    let upper = raw.trim().to_ascii_uppercase();
+    if upper == ALL_CAPABILITIES {
+        return Some(upper);
+    }
+    let candidate = upper.strip_prefix("CAP_").unwrap_or(&upper);
+    CAPABILITY_NAMES
+        .iter()
+        .find(|name| **name == candidate)
+        .map(|name| format!("CAP_{name}"))
+}
+
+/// Capability number for a canonical `CAP_` name.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn capability_number(canonical: &str) -> Option<u32> {
+    let name = canonical.strip_prefix("CAP_")?;
+    CAPABILITY_NAMES
+        .iter()
+        .position(|candidate| *candidate == name)
+        .map(|idx| idx as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_canonicalised_and_numbered() {
+        assert_eq!(
+            canonical_capability_name("net_bind_service").as_deref(),
+            Some("CAP_NET_BIND_SERVICE")
+        );
+        assert_eq!(
+            canonical_capability_name("CAP_SYS_ADMIN").as_deref(),
+            Some("CAP_SYS_ADMIN")
+        );
+        assert_eq!(canonical_capability_name("all").as_deref(), Some("ALL"));
+        assert_eq!(canonical_capability_name("NOPE"), None);
+        assert_eq!(capability_number("CAP_NET_BIND_SERVICE"), Some(10));
+        assert_eq!(capability_number("CAP_CHECKPOINT_RESTORE"), Some(40));
+    }
+}
@@ -1,8 +1,15 @@
+use crate::capabilities::{CAPABILITY_NAMES, canonical_capability_name};
+use crate::config::FileConfig;
+use crate::control::Request;
+use crate::exit_code::{ExitMapping, parse_exit_mapping};
 use crate::signals::{SIGNAL_NAMES, canonical_signal_name};
-use clap::Parser;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use std::{path::PathBuf, time::Duration};
+
+pub(crate) const MAIN_PROCESS_NAME: &str = "main";
 
 #[derive(Parser, Debug)]
-#[command(author, version, about)]
+#[command(author, version, about, args_conflicts_with_subcommands = true)]
 pub struct Cli {
     #[arg(short = 's', long)]
     pub subreaper: bool,
@@ -10,16 +17,90 @@ pub struct Cli {
     pub pdeath: Option<String>,
     #[arg(short = 'v', action = clap::ArgAction::Count)]
     pub verbosity: u8,
+    #[arg(long, env = "TINO_LOG_FORMAT", value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+    #[arg(long, value_enum, value_name = "STYLE")]
+    pub log_timestamps: Option<LogTimestamps>,
+    #[arg(long, value_name = "PATH", conflicts_with = "log_fd")]
+    pub log_file: Option<PathBuf>,
+    #[arg(long, value_name = "N")]
+    pub log_fd: Option<i32>,
     #[arg(short = 'w')]
     pub warn_on_reap: bool,
     #[arg(short = 'g')]
     pub pgroup_kill: bool,
+    #[arg(long, value_enum, value_name = "SCOPE")]
+    pub kill_scope: Option<KillScope>,
     #[arg(short = 'e', value_parser = clap::value_parser!(u8).range(0..=255))]
     pub remap_exit: Vec<u8>,
+    #[arg(long = "map-exit", value_parser = parse_exit_mapping, value_name = "PATTERN=CODE")]
+    pub map_exit: Vec<ExitMapping>,
+    #[arg(long)]
+    pub reraise: bool,
     #[arg(short = 't', long, default_value_t = 500)]
     pub grace_ms: u64,
+    #[arg(long, value_delimiter = ',', value_parser = parse_stop_stage, value_name = "SIG[:TIMEOUT],...")]
+    pub stop_sequence: Vec<StopStage>,
+    #[arg(long, value_parser = parse_signal, value_name = "SIG", conflicts_with = "stop_sequence")]
+    pub stop_signal: Option<String>,
+    #[arg(long, value_parser = parse_command, value_name = "CMD", conflicts_with_all = ["stop_signal", "stop_sequence"])]
+    pub stop_command: Option<Argv>,
+    #[arg(long, value_parser = parse_duration, value_name = "DURATION")]
+    pub stop_delay: Option<Duration>,
+    #[arg(long, value_name = "PATH")]
+    pub readiness_file: Option<PathBuf>,
+    #[arg(long, default_value_t = 0, value_name = "N")]
+    pub interrupt_kill_count: u32,
+    #[arg(long, value_parser = parse_duration, default_value = "5s", value_name = "DURATION")]
+    pub interrupt_kill_window: Duration,
+    #[arg(long, value_delimiter = ',', value_parser = parse_stop_stage, value_name = "SIG[:TIMEOUT],...")]
+    pub straggler_sequence: Vec<StopStage>,
     #[arg(short = 'l', long)]
     pub license: bool,
+    #[arg(long, env = "TINO_CONFIG", value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    #[arg(long = "sidecar", value_parser = parse_sidecar, value_name = "NAME=CMD")]
+    pub sidecars: Vec<ProcessSpec>,
+    #[arg(long, value_enum, default_value_t = ExitPolicy::Main)]
+    pub exit_policy: ExitPolicy,
+    #[arg(long, value_enum, default_value_t = RestartPolicy::No)]
+    pub restart: RestartPolicy,
+    #[arg(long, default_value_t = 100)]
+    pub restart_delay_ms: u64,
+    #[arg(long, default_value_t = 30_000)]
+    pub restart_max_delay_ms: u64,
+    #[arg(long, value_name = "N")]
+    pub max_restarts: Option<u32>,
+    #[arg(long, default_value_t = 10_000)]
+    pub restart_reset_ms: u64,
+    #[arg(long, value_name = "USER[:GROUP]")]
+    pub user: Option<String>,
+    #[arg(long)]
+    pub no_new_privs: bool,
+    #[arg(long, value_delimiter = ',', value_parser = parse_capability, value_name = "CAP,...")]
+    pub drop_caps: Vec<String>,
+    #[arg(long, value_delimiter = ',', value_parser = parse_capability, value_name = "CAP,...")]
+    pub keep_caps: Vec<String>,
+    #[arg(long, value_enum, default_value_t = OutputMode::Inherit)]
+    pub output: OutputMode,
+    #[arg(long)]
+    pub output_timestamps: bool,
+    #[arg(long, value_name = "PATH")]
+    pub stdout_file: Option<PathBuf>,
+    #[arg(long, value_name = "PATH")]
+    pub stderr_file: Option<PathBuf>,
+    #[arg(long, value_parser = parse_size, value_name = "SIZE")]
+    pub output_max_size: Option<u64>,
+    #[arg(long, default_value_t = 5, value_name = "N")]
+    pub output_keep: usize,
+    #[arg(long, env = "TINO_CONTROL_SOCKET", value_name = "PATH")]
+    pub control_socket: Option<PathBuf>,
+    #[arg(long = "rewrite", value_parser = parse_rewrite, value_name = "FROM:TO")]
+    pub rewrites: Vec<SignalRewrite>,
+    #[arg(long, value_delimiter = ',', value_parser = parse_forwardable_signal, value_name = "SIG,...")]
+    pub forward: Vec<String>,
+    #[arg(long, value_delimiter = ',', value_parser = parse_signal, value_name = "SIG,...")]
+    pub no_forward: Vec<String>,
     #[arg(long = "subreaper-env", env = "TINI_SUBREAPER", hide = true)]
     pub subreaper_env: Option<String>,
     #[arg(long = "pgroup-kill-env", env = "TINI_KILL_PROCESS_GROUP", hide = true)]
@@ -28,27 +109,384 @@ pub struct Cli {
     pub verbosity_env: Option<String>,
     #[arg(value_name = "CMD", trailing_var_arg = true)]
     pub cmd: Vec<String>,
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CliCommand {
+    /// Query or control a running tino through its control socket.
+    Ctl(CtlArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct CtlArgs {
+    #[arg(long, env = "TINO_CONTROL_SOCKET", value_name = "PATH")]
+    pub socket: PathBuf,
+    #[arg(long)]
+    pub json: bool,
+    #[command(subcommand)]
+    pub action: CtlAction,
+}
+
+#[derive(Subcommand, Clone, Debug, PartialEq, Eq)]
+pub enum CtlAction {
+    /// Show PIDs, uptime, restart counts and last exit status.
+    Status,
+    /// Send a signal to one process, or forward it to all.
+    Signal {
+        #[arg(value_parser = parse_signal)]
+        signal: String,
+        name: Option<String>,
+    },
+    /// Stop all processes, escalating to SIGKILL after the grace period.
+    Stop {
+        #[arg(long, value_name = "MS")]
+        grace: Option<u64>,
+    },
+    /// Restart one process, or all of them.
+    Restart { name: Option<String> },
+}
+
+impl CtlAction {
+    pub(crate) fn to_request(&self) -> Request {
+        match self.clone() {
+            CtlAction::Status => Request::Status,
+            CtlAction::Signal { signal, name } => Request::Signal {
+                signal,
+                target: name,
+            },
+            CtlAction::Stop { grace } => Request::Stop { grace_ms: grace },
+            CtlAction::Restart { name } => Request::Restart { target: name },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProcessSpec {
+    pub name: String,
+    pub cmd: Vec<String>,
+}
+
+/// Incoming signal `from` is forwarded as `to`, or dropped when `to` is `None`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignalRewrite {
+    pub from: String,
+    pub to: Option<String>,
+}
+
+/// A command split into words, e.g. from `--stop-command "nginx -s quit"`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Argv(pub Vec<String>);
+
+/// One step of a shutdown ladder: send `signal`, then wait up to `timeout`
+/// (`--grace-ms` when omitted) before moving on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StopStage {
+    pub signal: String,
+    pub timeout: Option<Duration>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Text,
+    /// One JSON object per event.
+    Json,
+    /// `key=value` pairs.
+    Logfmt,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputMode {
+    /// Children write straight to tino's stdout and stderr.
+    #[default]
+    Inherit,
+    /// Relay each line unchanged.
+    Plain,
+    /// Prefix each line with `[NAME] `.
+    Prefixed,
+    /// Wrap each line in a JSON record with process, pid and stream.
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum KillScope {
+    /// Only the supervised process itself.
+    Pid,
+    /// The process group created by `-g` (the process alone without it).
+    Group,
+    /// The process, its group and every descendant found in `/proc`.
+    Descendants,
+    /// Every descendant of tino, including orphans adopted with `-s` or as PID 1;
+    /// each straggler is logged with its command line.
+    All,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LogTimestamps {
+    /// Wall-clock time, e.g. `2024-05-01T12:00:00.123456Z`.
+    Rfc3339,
+    /// Seconds since tino started.
+    Monotonic,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ExitPolicy {
+    /// Exit when the main command exits; sidecars are terminated.
+    #[default]
+    Main,
+    /// Exit as soon as any supervised process exits.
+    Any,
+    /// Exit once every supervised process has exited.
+    All,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum RestartPolicy {
+    /// Never restart exited processes.
+    #[default]
+    No,
+    /// Restart processes whose (remapped) exit code is non-zero.
+    OnFailure,
+    /// Restart processes regardless of their exit code.
+    Always,
 }
 
 impl Cli {
+    /// Parses the command line and layers any `--config` file underneath it.
+    pub(crate) fn load() -> anyhow::Result<Self> {
+        let matches = Self::command().get_matches();
+        let mut cli = Self::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+        if let Some(path) = cli.config.clone() {
+            FileConfig::load(&path)?.apply(&mut cli, &matches)?;
+        }
+        Ok(cli)
+    }
+
     pub(crate) fn resolved_verbosity(&self) -> u8 {
         self.verbosity.min(3)
     }
+
+    /// `--kill-scope`, defaulting to the group with `-g` and the PID otherwise.
+    pub(crate) fn resolved_kill_scope(&self) -> KillScope {
+        self.kill_scope.unwrap_or(if self.pgroup_kill {
+            KillScope::Group
+        } else {
+            KillScope::Pid
+        })
+    }
 }
 
-fn parse_signal(raw: &str) -> Result<String, String> {
+pub(crate) fn parse_signal(raw: &str) -> Result<String, String> {
     let trimmed = raw.trim();
     if trimmed.is_empty() {
         return Err("signal name cannot be empty".into());
     }
-    if let Some(name) = canonical_signal_name(trimmed) {
-        Ok(format!("SIG{}", name))
-    } else {
-        Err(format!(
-            "invalid signal '{raw}'; supported values: {}",
+    canonical_signal_name(trimmed).ok_or_else(|| {
+        format!(
+            "invalid signal '{raw}'; supported values: {}, RTMIN+n, RTMAX-n or a signal number",
             SIGNAL_NAMES.join(", ")
-        ))
+        )
+    })
+}
+
+/// Accepts a capability with or without `CAP_`, or `ALL`.
+pub(crate) fn parse_capability(raw: &str) -> Result<String, String> {
+    canonical_capability_name(raw).ok_or_else(|| {
+        format!(
+            "invalid capability '{raw}'; supported values: ALL, {}",
+            CAPABILITY_NAMES.join(", ")
+        )
+    })
+}
+
+/// Parses a byte count with an optional binary `K`, `M` or `G` suffix (`10M`, `512KiB`).
+pub(crate) fn parse_size(raw: &str) -> Result<u64, String> {
+    let trimmed = raw.trim();
+    let upper = trimmed.to_ascii_uppercase();
+    let digits = upper
+        .strip_suffix("IB")
+        .or_else(|| upper.strip_suffix('B'))
+        .unwrap_or(&upper);
+    let (digits, shift) = match digits.as_bytes().last() {
+        Some(b'K') => (&digits[..digits.len() - 1], 10),
+        Some(b'M') => (&digits[..digits.len() - 1], 20),
+        Some(b'G') => (&digits[..digits.len() - 1], 30),
+        _ => (digits, 0),
+    };
+    digits
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(1 << shift))
+        .filter(|&n| n > 0)
+        .ok_or_else(|| format!("invalid size '{raw}'; expected e.g. 4096, 512K or 10M"))
+}
+
+/// Parses a duration such as `250ms`, `10s` or `2m`.
+pub(crate) fn parse_duration(raw: &str) -> Result<Duration, String> {
+    let trimmed = raw.trim();
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (digits, unit) = trimmed.split_at(split);
+    let value: u64 = digits
+        .parse()
+        .map_err(|_| format!("invalid duration '{raw}'; expected e.g. 500ms, 10s or 1m"))?;
+    match unit {
+        "ms" => Ok(Duration::from_millis(value)),
+        "s" => Ok(Duration::from_secs(value)),
+        "m" => Ok(Duration::from_secs(value.saturating_mul(60))),
+        _ => Err(format!(
+            "invalid duration '{raw}'; expected a unit of ms, s or m"
+        )),
+    }
+}
+
+pub(crate) fn parse_stop_stage(raw: &str) -> Result<StopStage, String> {
+    let (signal, timeout) = match raw.split_once(':') {
+        Some((signal, timeout)) => (signal, Some(parse_duration(timeout)?)),
+        None => (raw, None),
+    };
+    Ok(StopStage {
+        signal: parse_signal(signal)?,
+        timeout,
+    })
+}
+
+fn parse_command(raw: &str) -> Result<Argv, String> {
+    let argv = split_command_line(raw)?;
+    if argv.is_empty() {
+        return Err("command cannot be empty".into());
+    }
+    Ok(Argv(argv))
+}
+
+fn parse_sidecar(raw: &str) -> Result<ProcessSpec, String> {
+    let Some((name, cmdline)) = raw.split_once('=') else {
+        return Err(format!("invalid sidecar '{raw}'; expected NAME=CMD"));
+    };
+    let name = name.trim();
+    validate_sidecar_name(name)?;
+    let cmd = split_command_line(cmdline)?;
+    if cmd.is_empty() {
+        return Err(format!("sidecar '{name}' has an empty command"));
+    }
+    Ok(ProcessSpec {
+        name: name.to_string(),
+        cmd,
+    })
+}
+
+pub(crate) fn parse_forwardable_signal(raw: &str) -> Result<String, String> {
+    let name = parse_signal(raw)?;
+    check_interceptable(&name, "forwarded")?;
+    Ok(name)
+}
+
+fn check_interceptable(name: &str, action: &str) -> Result<(), String> {
+    match name {
+        "SIGKILL" | "SIGSTOP" => Err(format!(
+            "{name} cannot be intercepted, so it cannot be {action}"
+        )),
+        "SIGCHLD" => Err(format!(
+            "SIGCHLD is used to reap children, so it cannot be {action}"
+        )),
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn parse_rewrite(raw: &str) -> Result<SignalRewrite, String> {
+    let Some((from, to)) = raw.split_once(':') else {
+        return Err(format!("invalid rewrite '{raw}'; expected FROM:TO"));
+    };
+    let from = parse_signal(from)?;
+    check_interceptable(&from, "rewritten")?;
+    let to = match to.trim() {
+        "0" => None,
+        other => Some(parse_signal(other)?),
+    };
+    Ok(SignalRewrite { from, to })
+}
+
+pub(crate) fn validate_sidecar_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(format!(
+            "invalid sidecar name '{name}'; use letters, digits, '-', '_' or '.'"
+        ));
+    }
+    if name == MAIN_PROCESS_NAME {
+        return Err(format!("sidecar name '{MAIN_PROCESS_NAME}' is reserved"));
+    }
+    Ok(())
+}
+
+/// Splits a command line into words using POSIX shell quoting rules
+/// (single quotes, double quotes and backslash escapes) without expansion.
+pub(crate) fn split_command_line(raw: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err(format!("unterminated single quote in '{raw}'")),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => current.push(c),
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => return Err(format!("unterminated double quote in '{raw}'")),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err(format!("unterminated double quote in '{raw}'")),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                match chars.next() {
+                    Some(c) => current.push(c),
+                    None => return Err(format!("trailing backslash in '{raw}'")),
+                }
+            }
+            c => {
+                in_word = true;
+                current.push(c);
+            }
+        }
     }
+    if in_word {
+        words.push(current);
+    }
+    Ok(words)
 }
 
 #[cfg(test)]
@@ -63,6 +501,34 @@ mod tests {
         assert_eq!(parse_signal("TERM").unwrap(), "SIGTERM");
         assert_eq!(parse_signal("sigterm").unwrap(), "SIGTERM");
         assert_eq!(parse_signal("SIGUSR1").unwrap(), "SIGUSR1");
+        assert_eq!(parse_signal("xcpu").unwrap(), "SIGXCPU");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_signal_accepts_realtime_and_numeric_forms() {
+        assert_eq!(parse_signal("RTMIN+3").unwrap(), "SIGRTMIN+3");
+        assert_eq!(parse_signal("10").unwrap(), "SIGUSR1");
+        assert!(parse_forwardable_signal("SIGRTMAX-1").is_ok());
+        assert!(parse_forwardable_signal("CHLD").is_err());
+        assert!(parse_forwardable_signal("19").is_err());
+    }
+
+    #[test]
+    fn capability_lists_split_on_commas() {
+        let cli = Cli::try_parse_from([
+            "tino",
+            "--drop-caps",
+            "all",
+            "--keep-caps",
+            "net_bind_service,CAP_CHOWN",
+            "--",
+            "true",
+        ])
+        .unwrap();
+        assert_eq!(cli.drop_caps, ["ALL"]);
+        assert_eq!(cli.keep_caps, ["CAP_NET_BIND_SERVICE", "CAP_CHOWN"]);
+        assert!(Cli::try_parse_from(["tino", "--keep-caps", "NOPE", "--", "true"]).is_err());
     }
 
     #[test]
@@ -71,6 +537,152 @@ mod tests {
         assert!(parse_signal("").is_err());
     }
 
+    #[test]
+    fn ctl_subcommand_keeps_plain_commands_working() {
+        let cli = Cli::try_parse_from(["tino", "ctl", "--socket", "/run/t.sock", "signal", "hup"])
+            .unwrap();
+        let Some(CliCommand::Ctl(args)) = cli.command else {
+            panic!("expected ctl subcommand");
+        };
+        assert_eq!(
+            args.action.to_request(),
+            Request::Signal {
+                signal: "SIGHUP".into(),
+                target: None
+            }
+        );
+
+        let cli = Cli::try_parse_from(["tino", "sleep", "1"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.cmd, vec!["sleep", "1"]);
+
+        let cli = Cli::try_parse_from(["tino", "-g", "--", "ctl", "status"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.cmd, vec!["ctl", "status"]);
+    }
+
+    #[test]
+    fn split_command_line_honours_quotes() {
+        assert_eq!(
+            split_command_line(r#"vector --config '/etc/vector a.toml' "x \"y\"" z\ w"#).unwrap(),
+            vec!["vector", "--config", "/etc/vector a.toml", "x \"y\"", "z w"]
+        );
+        assert_eq!(split_command_line("  ").unwrap(), Vec::<String>::new());
+        assert!(split_command_line("echo 'oops").is_err());
+    }
+
+    #[test]
+    fn parse_rewrite_accepts_names_and_ignore() {
+        assert_eq!(
+            parse_rewrite("TERM:sigquit").unwrap(),
+            SignalRewrite {
+                from: "SIGTERM".into(),
+                to: Some("SIGQUIT".into())
+            }
+        );
+        assert_eq!(parse_rewrite("WINCH:0").unwrap().to, None);
+        assert!(parse_rewrite("TERM").is_err());
+        assert!(parse_rewrite("TERM:NOPE").is_err());
+        assert!(parse_rewrite("KILL:TERM").is_err());
+    }
+
+    #[test]
+    fn forward_lists_are_comma_separated() {
+        let cli = Cli::try_parse_from([
+            "tino",
+            "--forward",
+            "alrm,SIGPIPE",
+            "--no-forward",
+            "WINCH",
+            "--",
+            "/bin/true",
+        ])
+        .unwrap();
+        assert_eq!(cli.forward, vec!["SIGALRM", "SIGPIPE"]);
+        assert_eq!(cli.no_forward, vec!["SIGWINCH"]);
+        assert!(Cli::try_parse_from(["tino", "--forward", "KILL", "--", "/bin/true"]).is_err());
+    }
+
+    #[test]
+    fn parse_size_accepts_binary_suffixes() {
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert_eq!(parse_size("512k").unwrap(), 512 * 1024);
+        assert_eq!(parse_size("10M").unwrap(), 10 << 20);
+        assert_eq!(parse_size("1GiB").unwrap(), 1 << 30);
+        assert_eq!(parse_size("2KB").unwrap(), 2048);
+        for bad in ["", "0", "ten", "5T", "-1K"] {
+            assert!(parse_size(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn parse_stop_stage_accepts_optional_timeouts() {
+        assert_eq!(
+            parse_stop_stage("TERM:10s").unwrap(),
+            StopStage {
+                signal: "SIGTERM".into(),
+                timeout: Some(Duration::from_secs(10)),
+            }
+        );
+        assert_eq!(
+            parse_stop_stage("int:250ms").unwrap().timeout,
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(parse_stop_stage("KILL").unwrap().timeout, None);
+        assert_eq!(parse_duration("2m").unwrap(), Duration::from_secs(120));
+        for bad in ["TERM:", "TERM:10", "TERM:1h", "NOPE:1s", "TERM:-1s"] {
+            assert!(parse_stop_stage(bad).is_err(), "{bad}");
+        }
+
+        let cli =
+            Cli::try_parse_from(["tino", "--stop-sequence", "TERM:10s,INT:5s,KILL", "x"]).unwrap();
+        let signals: Vec<_> = cli
+            .stop_sequence
+            .iter()
+            .map(|s| s.signal.as_str())
+            .collect();
+        assert_eq!(signals, ["SIGTERM", "SIGINT", "SIGKILL"]);
+    }
+
+    #[test]
+    fn stop_actions_parse_and_conflict() {
+        let cli = Cli::try_parse_from(["tino", "--stop-command", "nginx -s 'quit'", "x"]).unwrap();
+        assert_eq!(
+            cli.stop_command,
+            Some(Argv(vec!["nginx".into(), "-s".into(), "quit".into()]))
+        );
+        let cli = Cli::try_parse_from(["tino", "--stop-signal", "quit", "x"]).unwrap();
+        assert_eq!(cli.stop_signal.as_deref(), Some("SIGQUIT"));
+
+        assert!(Cli::try_parse_from(["tino", "--stop-command", " ", "x"]).is_err());
+        assert!(
+            Cli::try_parse_from(["tino", "--stop-signal", "QUIT", "--stop-command", "a", "x"])
+                .is_err()
+        );
+        assert!(
+            Cli::try_parse_from([
+                "tino",
+                "--stop-signal",
+                "QUIT",
+                "--stop-sequence",
+                "TERM",
+                "x"
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn parse_sidecar_validates_name_and_command() {
+        let spec = parse_sidecar("logs=fluent-bit -c /etc/fb.conf").unwrap();
+        assert_eq!(spec.name, "logs");
+        assert_eq!(spec.cmd, vec!["fluent-bit", "-c", "/etc/fb.conf"]);
+        assert!(parse_sidecar("main=sleep 1").is_err());
+        assert!(parse_sidecar("bad name=sleep 1").is_err());
+        assert!(parse_sidecar("empty=").is_err());
+        assert!(parse_sidecar("no-separator").is_err());
+    }
+
     struct EnvVarsGuard {
         originals: Vec<(&'static str, Option<String>)>,
         _lock: MutexGuard<'static, ()>,
@@ -0,0 +1,517 @@
+//! Declarative `--config` file support.
+//!
+//! Keys mirror the long command-line options in kebab-case. Values from the file
+//! only fill options that were not given as flags or through `TINI_*` variables.
+
+use crate::cli::{
+    Argv, Cli, ProcessSpec, StopStage, parse_capability, parse_duration, parse_forwardable_signal,
+    parse_rewrite, parse_signal, parse_size, parse_stop_stage, split_command_line,
+    validate_sidecar_name,
+};
+use crate::exit_code::parse_exit_mapping;
+use anyhow::{Result, anyhow, bail};
+use clap::{ArgMatches, ValueEnum, parser::ValueSource};
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct FileConfig {
+    command: Option<CommandLine>,
+    subreaper: Option<bool>,
+    pdeath: Option<String>,
+    verbosity: Option<u8>,
+    log_format: Option<String>,
+    log_timestamps: Option<String>,
+    log_file: Option<PathBuf>,
+    log_fd: Option<i32>,
+    warn_on_reap: Option<bool>,
+    pgroup_kill: Option<bool>,
+    kill_scope: Option<String>,
+    remap_exit: Option<Vec<u8>>,
+    map_exit: Option<Vec<String>>,
+    reraise: Option<bool>,
+    grace_ms: Option<u64>,
+    stop_sequence: Option<Vec<String>>,
+    stop_signal: Option<String>,
+    stop_command: Option<CommandLine>,
+    stop_delay: Option<String>,
+    readiness_file: Option<PathBuf>,
+    interrupt_kill_count: Option<u32>,
+    interrupt_kill_window: Option<String>,
+    straggler_sequence: Option<Vec<String>>,
+    exit_policy: Option<String>,
+    restart: Option<String>,
+    restart_delay_ms: Option<u64>,
+    restart_max_delay_ms: Option<u64>,
+    max_restarts: Option<u32>,
+    restart_reset_ms: Option<u64>,
+    user: Option<String>,
+    no_new_privs: Option<bool>,
+    drop_caps: Option<Vec<String>>,
+    keep_caps: Option<Vec<String>>,
+    output: Option<String>,
+    output_timestamps: Option<bool>,
+    stdout_file: Option<PathBuf>,
+    stderr_file: Option<PathBuf>,
+    output_max_size: Option<String>,
+    output_keep: Option<usize>,
+    control_socket: Option<PathBuf>,
+    rewrite: Option<Vec<String>>,
+    forward: Option<Vec<String>>,
+    no_forward: Option<Vec<String>>,
+    sidecar: Vec<SidecarConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SidecarConfig {
+    name: String,
+    command: CommandLine,
+}
+
+/// A command given either as an argv array or as a single shell-quoted string.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum CommandLine {
+    Argv(Vec<String>),
+    Line(String),
+}
+
+impl CommandLine {
+    fn into_argv(self) -> Result<Vec<String>> {
+        match self {
+            CommandLine::Argv(argv) => Ok(argv),
+            CommandLine::Line(line) => split_command_line(&line).map_err(|e| anyhow!(e)),
+        }
+    }
+}
+
+impl FileConfig {
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let text =
+            fs::read_to_string(path).map_err(|e| anyhow!("read config {}: {e}", path.display()))?;
+        Self::parse(&text).map_err(|e| anyhow!("config {}: {e}", path.display()))
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        toml::from_str(text).map_err(|e| anyhow!(e.message().to_string()))
+    }
+
+    /// Fills `cli` fields that were left at their defaults.
+    pub(crate) fn apply(self, cli: &mut Cli, matches: &ArgMatches) -> Result<()> {
+        let unset = |id: &str| {
+            !matches!(
+                matches.value_source(id),
+                Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+            )
+        };
+
+        if let Some(command) = self.command
+            && cli.cmd.is_empty()
+        {
+            cli.cmd = command.into_argv()?;
+        }
+        if let Some(enabled) = self.subreaper
+            && unset("subreaper")
+        {
+            cli.subreaper = enabled;
+        }
+        if let Some(raw) = self.pdeath
+            && unset("pdeath")
+        {
+            cli.pdeath = Some(parse_signal(&raw).map_err(|e| anyhow!("pdeath: {e}"))?);
+        }
+        if let Some(level) = self.verbosity
+            && unset("verbosity")
+            && cli.verbosity_env.is_none()
+        {
+            cli.verbosity = level.min(3);
+        }
+        if let Some(raw) = self.log_format
+            && unset("log_format")
+        {
+            cli.log_format = parse_enum("log-format", &raw)?;
+        }
+        if let Some(raw) = self.log_timestamps
+            && unset("log_timestamps")
+        {
+            cli.log_timestamps = Some(parse_enum("log-timestamps", &raw)?);
+        }
+        // A destination given on the command line replaces the file's, whichever kind it is.
+        let log_destination_unset = unset("log_file") && unset("log_fd");
+        if let Some(path) = self.log_file
+            && log_destination_unset
+        {
+            cli.log_file = Some(path);
+        }
+        if let Some(fd) = self.log_fd
+            && log_destination_unset
+        {
+            cli.log_fd = Some(fd);
+        }
+        if let Some(enabled) = self.warn_on_reap
+            && unset("warn_on_reap")
+        {
+            cli.warn_on_reap = enabled;
+        }
+        if let Some(enabled) = self.pgroup_kill
+            && unset("pgroup_kill")
+        {
+            cli.pgroup_kill = enabled;
+        }
+        if let Some(raw) = self.kill_scope
+            && unset("kill_scope")
+        {
+            cli.kill_scope = Some(parse_enum("kill-scope", &raw)?);
+        }
+        if let Some(codes) = self.remap_exit
+            && unset("remap_exit")
+        {
+            cli.remap_exit = codes;
+        }
+        if let Some(rules) = self.map_exit
+            && unset("map_exit")
+        {
+            cli.map_exit = rules
+                .iter()
+                .map(|raw| parse_exit_mapping(raw).map_err(|e| anyhow!("map-exit: {e}")))
+                .collect::<Result<_>>()?;
+        }
+        if let Some(enabled) = self.reraise
+            && unset("reraise")
+        {
+            cli.reraise = enabled;
+        }
+        if let Some(ms) = self.grace_ms
+            && unset("grace_ms")
+        {
+            cli.grace_ms = ms;
+        }
+        // The stop options are alternatives; one given on the command line replaces the file's.
+        let stop_unset = unset("stop_sequence") && unset("stop_signal") && unset("stop_command");
+        if let Some(stages) = self.stop_sequence
+            && stop_unset
+        {
+            cli.stop_sequence = parse_stop_sequence("stop-sequence", &stages)?;
+        }
+        if let Some(raw) = self.stop_signal
+            && stop_unset
+        {
+            cli.stop_signal = Some(parse_signal(&raw).map_err(|e| anyhow!("stop-signal: {e}"))?);
+        }
+        if let Some(command) = self.stop_command
+            && stop_unset
+        {
+            let argv = command.into_argv()?;
+            if argv.is_empty() {
+                bail!("stop-command cannot be empty");
+            }
+            cli.stop_command = Some(Argv(argv));
+        }
+        let stop_options = [
+            !cli.stop_sequence.is_empty(),
+            cli.stop_signal.is_some(),
+            cli.stop_command.is_some(),
+        ];
+        if stop_options.iter().filter(|&&set| set).count() > 1 {
+            bail!("stop-sequence, stop-signal and stop-command cannot be combined");
+        }
+        if let Some(raw) = self.stop_delay
+            && unset("stop_delay")
+        {
+            cli.stop_delay = Some(parse_duration(&raw).map_err(|e| anyhow!("stop-delay: {e}"))?);
+        }
+        if let Some(path) = self.readiness_file
+            && unset("readiness_file")
+        {
+            cli.readiness_file = Some(path);
+        }
+        if let Some(count) = self.interrupt_kill_count
+            && unset("interrupt_kill_count")
+        {
+            cli.interrupt_kill_count = count;
+        }
+        if let Some(raw) = self.interrupt_kill_window
+            && unset("interrupt_kill_window")
+        {
+            cli.interrupt_kill_window =
+                parse_duration(&raw).map_err(|e| anyhow!("interrupt-kill-window: {e}"))?;
+        }
+        if let Some(stages) = self.straggler_sequence
+            && unset("straggler_sequence")
+        {
+            cli.straggler_sequence = parse_stop_sequence("straggler-sequence", &stages)?;
+        }
+        if let Some(raw) = self.exit_policy
+            && unset("exit_policy")
+        {
+            cli.exit_policy = parse_enum("exit-policy", &raw)?;
+        }
+        if let Some(raw) = self.restart
+            && unset("restart")
+        {
+            cli.restart = parse_enum("restart", &raw)?;
+        }
+        if let Some(ms) = self.restart_delay_ms
+            && unset("restart_delay_ms")
+        {
+            cli.restart_delay_ms = ms;
+        }
+        if let Some(ms) = self.restart_max_delay_ms
+            && unset("restart_max_delay_ms")
+        {
+            cli.restart_max_delay_ms = ms;
+        }
+        if let Some(max) = self.max_restarts
+            && unset("max_restarts")
+        {
+            cli.max_restarts = Some(max);
+        }
+        if let Some(ms) = self.restart_reset_ms
+            && unset("restart_reset_ms")
+        {
+            cli.restart_reset_ms = ms;
+        }
+        if let Some(user) = self.user
+            && unset("user")
+        {
+            cli.user = Some(user);
+        }
+        if let Some(enabled) = self.no_new_privs
+            && unset("no_new_privs")
+        {
+            cli.no_new_privs = enabled;
+        }
+        if let Some(names) = self.drop_caps
+            && unset("drop_caps")
+        {
+            cli.drop_caps = parse_name_list("drop-caps", &names, parse_capability)?;
+        }
+        if let Some(names) = self.keep_caps
+            && unset("keep_caps")
+        {
+            cli.keep_caps = parse_name_list("keep-caps", &names, parse_capability)?;
+        }
+        if let Some(raw) = self.output
+            && unset("output")
+        {
+            cli.output = parse_enum("output", &raw)?;
+        }
+        if let Some(enabled) = self.output_timestamps
+            && unset("output_timestamps")
+        {
+            cli.output_timestamps = enabled;
+        }
+        if let Some(path) = self.stdout_file
+            && unset("stdout_file")
+        {
+            cli.stdout_file = Some(path);
+        }
+        if let Some(path) = self.stderr_file
+            && unset("stderr_file")
+        {
+            cli.stderr_file = Some(path);
+        }
+        if let Some(raw) = self.output_max_size
+            && unset("output_max_size")
+        {
+            cli.output_max_size =
+                Some(parse_size(&raw).map_err(|e| anyhow!("output-max-size: {e}"))?);
+        }
+        if let Some(keep) = self.output_keep
+            && unset("output_keep")
+        {
+            cli.output_keep = keep;
+        }
+        if let Some(path) = self.control_socket
+            && unset("control_socket")
+        {
+            cli.control_socket = Some(path);
+        }
+        if let Some(rewrites) = self.rewrite
+            && unset("rewrites")
+        {
+            cli.rewrites = rewrites
+                .iter()
+                .map(|raw| parse_rewrite(raw).map_err(|e| anyhow!("rewrite: {e}")))
+                .collect::<Result<_>>()?;
+        }
+        if let Some(names) = self.forward
+            && unset("forward")
+        {
+            cli.forward = parse_name_list("forward", &names, parse_forwardable_signal)?;
+        }
+        if let Some(names) = self.no_forward
+            && unset("no_forward")
+        {
+            cli.no_forward = parse_name_list("no-forward", &names, parse_signal)?;
+        }
+        if !self.sidecar.is_empty() && unset("sidecars") {
+            cli.sidecars = self
+                .sidecar
+                .into_iter()
+                .map(SidecarConfig::into_spec)
+                .collect::<Result<_>>()?;
+        }
+        Ok(())
+    }
+}
+
+impl SidecarConfig {
+    fn into_spec(self) -> Result<ProcessSpec> {
+        validate_sidecar_name(&self.name).map_err(|e| anyhow!(e))?;
+        let cmd = self.command.into_argv()?;
+        if cmd.is_empty() {
+            bail!("sidecar '{}' has an empty command", self.name);
+        }
+        Ok(ProcessSpec {
+            name: self.name,
+            cmd,
+        })
+    }
+}
+
+fn parse_name_list(
+    key: &str,
+    names: &[String],
+    parse: fn(&str) -> std::result::Result<String, String>,
+) -> Result<Vec<String>> {
+    names
+        .iter()
+        .map(|raw| parse(raw).map_err(|e| anyhow!("{key}: {e}")))
+        .collect()
+}
+
+/// Accepts one stage per entry or comma-separated stages, as on the command line.
+fn parse_stop_sequence(key: &str, stages: &[String]) -> Result<Vec<StopStage>> {
+    stages
+        .iter()
+        .flat_map(|entry| entry.split(','))
+        .map(|raw| parse_stop_stage(raw).map_err(|e| anyhow!("{key}: {e}")))
+        .collect()
+}
+
+fn parse_enum<T: ValueEnum>(key: &str, raw: &str) -> Result<T> {
+    T::from_str(raw, true).map_err(|_| {
+        let allowed: Vec<_> = T::value_variants()
+            .iter()
+            .filter_map(|v| v.to_possible_value())
+            .map(|v| v.get_name().to_string())
+            .collect();
+        anyhow!(
+            "invalid {key} '{raw}'; expected one of: {}",
+            allowed.join(", ")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{ExitPolicy, RestartPolicy};
+    use clap::{CommandFactory, FromArgMatches};
+
+    fn parse_args(args: &[&str]) -> (Cli, ArgMatches) {
+        let matches = Cli::command().try_get_matches_from(args).unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+        (cli, matches)
+    }
+
+    const SAMPLE: &str = r#"
+        command = ["nginx", "-g", "daemon off;"]
+        subreaper = true
+        pdeath = "usr1"
+        grace-ms = 2000
+        stop-sequence = ["TERM:10s", "INT:5s,KILL"]
+        remap-exit = [143]
+        map-exit = ["SIGTERM=0", "*=2"]
+        exit-policy = "any"
+        restart = "on-failure"
+        rewrite = ["TERM:QUIT", "WINCH:0"]
+        forward = ["ALRM"]
+        no-forward = ["TTIN", "TTOU"]
+
+        [[sidecar]]
+        name = "logs"
+        command = "fluent-bit -c '/etc/fluent bit.conf'"
+    "#;
+
+    #[test]
+    fn file_values_fill_defaults() {
+        let (mut cli, matches) = parse_args(&["tino"]);
+        FileConfig::parse(SAMPLE)
+            .unwrap()
+            .apply(&mut cli, &matches)
+            .unwrap();
+
+        assert_eq!(cli.cmd, vec!["nginx", "-g", "daemon off;"]);
+        assert!(cli.subreaper);
+        assert_eq!(cli.pdeath.as_deref(), Some("SIGUSR1"));
+        assert_eq!(cli.grace_ms, 2000);
+        assert_eq!(cli.stop_sequence.len(), 3);
+        assert_eq!(cli.stop_sequence[1].signal, "SIGINT");
+        assert_eq!(cli.remap_exit, vec![143]);
+        assert_eq!(cli.map_exit.len(), 2);
+        assert_eq!(cli.exit_policy, ExitPolicy::Any);
+        assert_eq!(cli.restart, RestartPolicy::OnFailure);
+        assert_eq!(cli.rewrites.len(), 2);
+        assert_eq!(cli.rewrites[0].to.as_deref(), Some("SIGQUIT"));
+        assert_eq!(cli.forward, vec!["SIGALRM"]);
+        assert_eq!(cli.no_forward, vec!["SIGTTIN", "SIGTTOU"]);
+        assert_eq!(cli.sidecars.len(), 1);
+        assert_eq!(
+            cli.sidecars[0].cmd,
+            vec!["fluent-bit", "-c", "/etc/fluent bit.conf"]
+        );
+    }
+
+    #[test]
+    fn flags_override_file_values() {
+        let (mut cli, matches) =
+            parse_args(&["tino", "-t", "100", "--restart", "always", "--", "/bin/app"]);
+        FileConfig::parse(SAMPLE)
+            .unwrap()
+            .apply(&mut cli, &matches)
+            .unwrap();
+
+        assert_eq!(cli.cmd, vec!["/bin/app"]);
+        assert_eq!(cli.grace_ms, 100);
+        assert_eq!(cli.restart, RestartPolicy::Always);
+        assert!(cli.subreaper);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        assert!(FileConfig::parse("unknown-key = 1").is_err());
+        assert!(FileConfig::parse("grace-ms = \"soon\"").is_err());
+
+        let (mut cli, matches) = parse_args(&["tino"]);
+        assert!(
+            FileConfig::parse(
+                "stop-signal = \"QUIT\"\nstop-command = [\"nginx\", \"-s\", \"quit\"]"
+            )
+            .unwrap()
+            .apply(&mut cli, &matches)
+            .is_err()
+        );
+
+        let (mut cli, matches) = parse_args(&["tino"]);
+        let err = FileConfig::parse("restart = \"sometimes\"")
+            .unwrap()
+            .apply(&mut cli, &matches)
+            .unwrap_err();
+        assert!(format!("{err}").contains("on-failure"), "{err}");
+
+        let (mut cli, matches) = parse_args(&["tino"]);
+        assert!(
+            FileConfig::parse("pdeath = \"NOPE\"")
+                .unwrap()
+                .apply(&mut cli, &matches)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,273 @@
+//! Line-based protocol spoken on the `--control-socket`.
+//!
+//! Clients send one command per connection (`status`, `signal <SIG> [NAME]`,
+//! `stop [--grace MS]`, `restart [NAME]`) and receive a single JSON line back.
+
+use crate::cli::parse_signal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Request {
+    Status,
+    Signal {
+        signal: String,
+        target: Option<String>,
+    },
+    Stop {
+        grace_ms: Option<u64>,
+    },
+    Restart {
+        target: Option<String>,
+    },
+}
+
+impl Request {
+    pub(crate) fn parse(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+        let command = words.next().ok_or("empty command")?;
+        let args: Vec<&str> = words.collect();
+        let request = match (command.to_ascii_lowercase().as_str(), args.as_slice()) {
+            ("status", []) => Request::Status,
+            ("signal", [signal]) | ("signal", [signal, _]) => Request::Signal {
+                signal: parse_signal(signal)?,
+                target: args.get(1).map(|name| name.to_string()),
+            },
+            ("stop", []) => Request::Stop { grace_ms: None },
+            ("stop", ["--grace", ms]) => Request::Stop {
+                grace_ms: Some(parse_grace(ms)?),
+            },
+            ("stop", [flag]) if flag.starts_with("--grace=") => Request::Stop {
+                grace_ms: Some(parse_grace(&flag["--grace=".len()..])?),
+            },
+            ("restart", []) => Request::Restart { target: None },
+            ("restart", [name]) => Request::Restart {
+                target: Some(name.to_string()),
+            },
+            ("status" | "signal" | "stop" | "restart", _) => {
+                return Err(format!("invalid arguments for '{command}'"));
+            }
+            _ => return Err(format!("unknown command '{command}'")),
+        };
+        Ok(request)
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Request::Status => write!(f, "status"),
+            Request::Signal { signal, target } => {
+                write!(f, "signal {signal}")?;
+                if let Some(target) = target {
+                    write!(f, " {target}")?;
+                }
+                Ok(())
+            }
+            Request::Stop { grace_ms: None } => write!(f, "stop"),
+            Request::Stop { grace_ms: Some(ms) } => write!(f, "stop --grace {ms}"),
+            Request::Restart { target: None } => write!(f, "restart"),
+            Request::Restart {
+                target: Some(target),
+            } => write!(f, "restart {target}"),
+        }
+    }
+}
+
+fn parse_grace(raw: &str) -> Result<u64, String> {
+    raw.parse()
+        .map_err(|_| format!("invalid grace period '{raw}'; expected milliseconds"))
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<Status>,
+}
+
+impl Response {
+    pub(crate) fn ok() -> Self {
+        Self {
+            ok: true,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(message.into()),
+            status: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Status {
+    pub pid: u32,
+    pub uptime_ms: u64,
+    pub stopping: bool,
+    pub processes: Vec<ProcessStatus>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ProcessStatus {
+    pub name: String,
+    pub pid: Option<i32>,
+    pub running: bool,
+    pub uptime_ms: Option<u64>,
+    pub restarts: u32,
+    pub last_exit: Option<ExitStatus>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ExitStatus {
+    pub code: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<String>,
+}
+
+impl Status {
+    /// Human-readable summary used by `tino ctl status`.
+    pub(crate) fn render(&self) -> String {
+        let mut out = format!(
+            "tino PID {}, up {}{}\n",
+            self.pid,
+            format_uptime(self.uptime_ms),
+            if self.stopping { ", stopping" } else { "" }
+        );
+        let width = self
+            .processes
+            .iter()
+            .map(|p| p.name.len())
+            .chain(std::iter::once("NAME".len()))
+            .max()
+            .unwrap_or(0);
+        out.push_str(&format!(
+            "{:<width$}  {:>7}  {:<7}  {:>9}  {:>8}  LAST EXIT\n",
+            "NAME", "PID", "STATE", "UPTIME", "RESTARTS"
+        ));
+        for process in &self.processes {
+            let pid = process
+                .pid
+                .map_or_else(|| "-".into(), |pid| pid.to_string());
+            let state = if process.running { "running" } else { "exited" };
+            let uptime = process.uptime_ms.map_or_else(|| "-".into(), format_uptime);
+            let last_exit = match &process.last_exit {
+                None => "-".to_string(),
+                Some(ExitStatus {
+                    code,
+                    signal: Some(signal),
+                }) => format!("{code} ({signal})"),
+                Some(ExitStatus { code, signal: None }) => code.to_string(),
+            };
+            out.push_str(&format!(
+                "{:<width$}  {:>7}  {:<7}  {:>9}  {:>8}  {}\n",
+                process.name, pid, state, uptime, process.restarts, last_exit
+            ));
+        }
+        out
+    }
+}
+
+fn format_uptime(ms: u64) -> String {
+    let secs = ms / 1000;
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m{:02}s", secs / 60, secs % 60),
+        3600..86400 => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d{:02}h", secs / 86400, secs % 86400 / 3600),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_round_trip_through_text() {
+        for line in [
+            "status",
+            "signal SIGHUP",
+            "signal SIGUSR1 logs",
+            "stop",
+            "stop --grace 2500",
+            "restart",
+            "restart logs",
+        ] {
+            let request = Request::parse(line).unwrap();
+            assert_eq!(request.to_string(), line);
+        }
+        assert_eq!(
+            Request::parse("STOP --grace=10").unwrap(),
+            Request::Stop { grace_ms: Some(10) }
+        );
+        assert_eq!(
+            Request::parse("signal hup").unwrap(),
+            Request::Signal {
+                signal: "SIGHUP".into(),
+                target: None
+            }
+        );
+    }
+
+    #[test]
+    fn malformed_requests_are_rejected() {
+        assert!(Request::parse("").is_err());
+        assert!(Request::parse("reboot").is_err());
+        assert!(Request::parse("signal").is_err());
+        assert!(Request::parse("signal NOPE").is_err());
+        assert!(Request::parse("stop --grace soon").is_err());
+        assert!(Request::parse("status now").is_err());
+    }
+
+    #[test]
+    fn status_renders_as_table() {
+        let status = Status {
+            pid: 1,
+            uptime_ms: 3_723_000,
+            stopping: false,
+            processes: vec![
+                ProcessStatus {
+                    name: "main".into(),
+                    pid: Some(7),
+                    running: true,
+                    uptime_ms: Some(65_000),
+                    restarts: 0,
+                    last_exit: None,
+                },
+                ProcessStatus {
+                    name: "log-shipper".into(),
+                    pid: Some(9),
+                    running: false,
+                    uptime_ms: None,
+                    restarts: 2,
+                    last_exit: Some(ExitStatus {
+                        code: 137,
+                        signal: Some("SIGKILL".into()),
+                    }),
+                },
+            ],
+        };
+        let rendered = status.render();
+        let lines: Vec<_> = rendered.lines().collect();
+        assert_eq!(lines[0], "tino PID 1, up 1h02m");
+        assert_eq!(
+            lines[2],
+            "main               7  running      1m05s         0  -"
+        );
+        assert_eq!(
+            lines[3],
+            "log-shipper        9  exited           -         2  137 (SIGKILL)"
+        );
+    }
+
+    #[test]
+    fn error_responses_omit_status() {
+        let json = serde_json::to_string(&Response::error("nope")).unwrap();
+        assert_eq!(json, r#"{"ok":false,"error":"nope"}"#);
+    }
+}
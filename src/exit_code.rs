@@ -0,0 +1,162 @@
+//! Exit status remapping for `-e` and `--map-exit`.
+
+use crate::cli::parse_signal;
+
+/// What a `--map-exit` rule matches.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExitPattern {
+    /// An inclusive range of exit codes, compared against `128+n` for signal deaths.
+    Codes(u8, u8),
+    /// Death by this signal (canonical `SIG` name).
+    Signal(String),
+    /// Any non-zero status not matched by another rule.
+    Any,
+}
+
+/// A `--map-exit PATTERN=CODE` rule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExitMapping {
+    pub pattern: ExitPattern,
+    pub code: u8,
+}
+
+pub(crate) fn parse_exit_mapping(raw: &str) -> Result<ExitMapping, String> {
+    let Some((pattern, code)) = raw.rsplit_once('=') else {
+        return Err(format!(
+            "invalid exit mapping '{raw}'; expected PATTERN=CODE"
+        ));
+    };
+    let code = code
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid exit code '{}'; expected 0-255", code.trim()))?;
+    Ok(ExitMapping {
+        pattern: parse_exit_pattern(pattern.trim())?,
+        code,
+    })
+}
+
+fn parse_exit_pattern(raw: &str) -> Result<ExitPattern, String> {
+    let parse_code = |s: &str| {
+        s.trim()
+            .parse::<u8>()
+            .map_err(|_| format!("invalid exit code '{}'; expected 0-255", s.trim()))
+    };
+    if raw == "*" {
+        return Ok(ExitPattern::Any);
+    }
+    if raw.starts_with(|c: char| c.is_ascii_digit()) {
+        let (lo, hi) = match raw.split_once('-') {
+            Some((lo, hi)) => (parse_code(lo)?, parse_code(hi)?),
+            None => {
+                let code = parse_code(raw)?;
+                (code, code)
+            }
+        };
+        if lo > hi {
+            return Err(format!("invalid exit code range '{raw}'"));
+        }
+        return Ok(ExitPattern::Codes(lo, hi));
+    }
+    parse_signal(raw).map(ExitPattern::Signal)
+}
+
+/// Resolved remapping table: signal rules first, then code rules in the order
+/// given (`--map-exit` before `-e`), then the `*` fallback.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ExitCodeMap {
+    signals: Vec<(String, u8)>,
+    codes: Vec<(u8, u8, u8)>,
+    fallback: Option<u8>,
+}
+
+impl ExitCodeMap {
+    pub(crate) fn new(expect_zero: &[u8], mappings: &[ExitMapping]) -> Self {
+        let mut map = Self::default();
+        for mapping in mappings {
+            match &mapping.pattern {
+                ExitPattern::Codes(lo, hi) => map.codes.push((*lo, *hi, mapping.code)),
+                ExitPattern::Signal(name) => map.signals.push((name.clone(), mapping.code)),
+                ExitPattern::Any => map.fallback = Some(mapping.code),
+            }
+        }
+        map.codes
+            .extend(expect_zero.iter().map(|&code| (code, code, 0)));
+        map
+    }
+
+    /// Maps an exit status; `signal` names the signal that killed the process.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub(crate) fn apply(&self, code: i32, signal: Option<&str>) -> i32 {
+        if let Some(signal) = signal
+            && let Some((_, mapped)) = self.signals.iter().find(|(name, _)| name == signal)
+        {
+            return i32::from(*mapped);
+        }
+        if let Ok(byte) = u8::try_from(code)
+            && let Some((_, _, mapped)) = self
+                .codes
+                .iter()
+                .find(|(lo, hi, _)| (*lo..=*hi).contains(&byte))
+        {
+            return i32::from(*mapped);
+        }
+        match self.fallback {
+            Some(mapped) if code != 0 => i32::from(mapped),
+            _ => code,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(expect_zero: &[u8], rules: &[&str]) -> ExitCodeMap {
+        let mappings: Vec<_> = rules
+            .iter()
+            .map(|raw| parse_exit_mapping(raw).unwrap())
+            .collect();
+        ExitCodeMap::new(expect_zero, &mappings)
+    }
+
+    #[test]
+    fn patterns_parse() {
+        assert_eq!(
+            parse_exit_mapping("143=0").unwrap().pattern,
+            ExitPattern::Codes(143, 143)
+        );
+        assert_eq!(
+            parse_exit_mapping("1-9=1").unwrap().pattern,
+            ExitPattern::Codes(1, 9)
+        );
+        assert_eq!(
+            parse_exit_mapping("term=0").unwrap().pattern,
+            ExitPattern::Signal("SIGTERM".into())
+        );
+        assert_eq!(parse_exit_mapping("*=2").unwrap().pattern, ExitPattern::Any);
+        for bad in ["143", "9-1=0", "1=256", "300=1", "NOPE=1", "=1"] {
+            assert!(parse_exit_mapping(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn specific_rules_win_over_fallback() {
+        let map = table(&[], &["*=2", "1-9=1", "SIGTERM=0", "143=5"]);
+        assert_eq!(map.apply(0, None), 0);
+        assert_eq!(map.apply(3, None), 1);
+        assert_eq!(map.apply(42, None), 2);
+        assert_eq!(map.apply(143, Some("SIGTERM")), 0);
+        assert_eq!(map.apply(143, None), 5);
+        assert_eq!(map.apply(137, Some("SIGKILL")), 2);
+    }
+
+    #[test]
+    fn remap_exit_zeroes_listed_codes() {
+        let map = table(&[3], &["3=4"]);
+        assert_eq!(map.apply(3, None), 4);
+        let map = table(&[3], &[]);
+        assert_eq!(map.apply(3, None), 0);
+        assert_eq!(map.apply(5, None), 5);
+    }
+}
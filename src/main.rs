@@ -4,21 +4,24 @@
 
 #![deny(unsafe_op_in_unsafe_fn)]
 
+mod capabilities;
 mod cli;
+mod config;
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+mod control;
+mod exit_code;
 mod platform;
 mod signals;
 
-use clap::Parser;
 use tracing::error;
 
 pub(crate) const LICENSE_TEXT: &str = include_str!("../LICENSE");
 
 fn main() {
-    let cli = cli::Cli::parse();
-
-    let exit_code = match platform::run(cli) {
+    let exit_code = match cli::Cli::load().and_then(platform::run) {
         Ok(code) => code,
         Err(err) => {
+            platform::init_logging(0, platform::LogOptions::default());
             error!(error = %err, "tino failed");
             1
         }
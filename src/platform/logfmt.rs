@@ -0,0 +1,150 @@
+//! `--log-format logfmt` event formatter: `level=info msg="..." key=value`.
+
+use super::LogTimer;
+use std::fmt;
+use tracing::{
+    Event, Subscriber,
+    field::{Field, Visit},
+};
+use tracing_subscriber::{
+    fmt::{FmtContext, FormatEvent, FormatFields, format::Writer, time::FormatTime},
+    registry::LookupSpan,
+};
+
+pub(super) struct Logfmt {
+    pub timer: Option<LogTimer>,
+}
+
+impl<S, N> FormatEvent<S, N> for Logfmt
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        _ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        if let Some(timer) = &self.timer {
+            writer.write_str("time=")?;
+            timer.format_time(&mut writer)?;
+            writer.write_char(' ')?;
+        }
+        let level = event.metadata().level().as_str().to_ascii_lowercase();
+        write!(writer, "level={level}")?;
+        if let Some(message) = &fields.message {
+            write_pair(&mut writer, "msg", message)?;
+        }
+        for (key, value) in &fields.pairs {
+            write_pair(&mut writer, key, value)?;
+        }
+        writeln!(writer)
+    }
+}
+
+#[derive(Default)]
+struct Fields {
+    message: Option<String>,
+    pairs: Vec<(&'static str, String)>,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.push(field, format!("{value:?}"));
+    }
+}
+
+impl Fields {
+    fn push(&mut self, field: &Field, value: String) {
+        if field.name() == "message" {
+            self.message = Some(value);
+        } else {
+            self.pairs.push((field.name(), value));
+        }
+    }
+}
+
+fn write_pair(writer: &mut Writer<'_>, key: &str, value: &str) -> fmt::Result {
+    write!(writer, " {key}=")?;
+    let needs_quotes = value.is_empty()
+        || value
+            .chars()
+            .any(|c| c == ' ' || c == '=' || c == '"' || c.is_control());
+    if !needs_quotes {
+        return writer.write_str(value);
+    }
+    writer.write_char('"')?;
+    for c in value.chars() {
+        match c {
+            '"' => writer.write_str("\\\"")?,
+            '\\' => writer.write_str("\\\\")?,
+            '\n' => writer.write_str("\\n")?,
+            c if c.is_control() => write!(writer, "\\u{{{:04x}}}", c as u32)?,
+            c => writer.write_char(c)?,
+        }
+    }
+    writer.write_char('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+    use tracing::info;
+    use tracing_subscriber::fmt::MakeWriter;
+
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Capture {
+        type Writer = Capture;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn events_render_as_logfmt() {
+        let capture = Capture::default();
+        let subscriber = tracing_subscriber::fmt()
+            .event_format(Logfmt { timer: None })
+            .with_writer(capture.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            info!(pid = 42, signal = %"SIGTERM", "forwarding signal");
+            info!(path = "/a b", note = "say \"hi\"", "quoted");
+        });
+        let output = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(
+            lines[0],
+            r#"level=info msg="forwarding signal" pid=42 signal=SIGTERM"#
+        );
+        assert_eq!(
+            lines[1],
+            r#"level=info msg=quoted path="/a b" note="say \"hi\"""#
+        );
+    }
+}
@@ -1,11 +1,30 @@
-use crate::{LICENSE_TEXT, cli::Cli};
-use anyhow::{Result, bail};
+use crate::{
+    LICENSE_TEXT,
+    cli::{Cli, CliCommand, CtlArgs, LogFormat, LogTimestamps},
+    exit_code::ExitCodeMap,
+};
+use anyhow::{Context, Result, bail};
 use once_cell::sync::OnceCell;
 use std::collections::HashSet;
-use std::io::{self, Write};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    os::fd::{FromRawFd, RawFd},
+    sync::Arc,
+    time::Instant,
+};
 use tracing::{debug, warn};
-use tracing_subscriber::{filter::EnvFilter, fmt};
+use tracing_subscriber::{
+    filter::EnvFilter,
+    fmt::{
+        self,
+        format::Writer,
+        time::{FormatTime, SystemTime},
+        writer::BoxMakeWriter,
+    },
+};
 
+mod logfmt;
 #[cfg(not(target_os = "linux"))]
 mod stub;
 #[cfg(target_os = "linux")]
@@ -14,6 +33,9 @@ mod unix;
 static LOGGER: OnceCell<()> = OnceCell::new();
 
 pub fn run(mut cli: Cli) -> Result<i32> {
+    if let Some(CliCommand::Ctl(args)) = &cli.command {
+        return run_ctl(args);
+    }
     if cli.license {
         print!("{LICENSE_TEXT}");
         let _ = io::stdout().flush();
@@ -24,20 +46,33 @@ pub fn run(mut cli: Cli) -> Result<i32> {
     if cli.cmd.is_empty() {
         bail!("missing CMD (use --help)");
     }
+    let mut sidecar_names = HashSet::new();
+    for sidecar in &cli.sidecars {
+        if !sidecar_names.insert(sidecar.name.as_str()) {
+            bail!("duplicate sidecar name '{}'", sidecar.name);
+        }
+    }
+    let mut rewritten = HashSet::new();
+    for rewrite in &cli.rewrites {
+        if !rewritten.insert(rewrite.from.as_str()) {
+            bail!("duplicate --rewrite for {}", rewrite.from);
+        }
+    }
     let warn_implies_subreaper = cli.warn_on_reap && !cli.subreaper;
     if warn_implies_subreaper {
         cli.subreaper = true;
     }
 
+    let log_options = LogOptions::from_cli(&cli)?;
     let verbosity = cli.resolved_verbosity();
-    init_logging(verbosity);
+    init_logging(verbosity, log_options);
     overrides.emit();
     if warn_implies_subreaper {
         debug!("subreaper enabled via --warn-on-reap");
     }
 
-    let expect_zero: HashSet<u8> = cli.remap_exit.iter().copied().collect();
-    run_impl(cli, expect_zero)
+    let exit_map = ExitCodeMap::new(&cli.remap_exit, &cli.map_exit);
+    run_impl(cli, exit_map)
 }
 
 #[derive(Default)]
@@ -137,7 +172,87 @@ fn interpret_env_flag(raw: &str) -> std::result::Result<bool, String> {
     Err(owned)
 }
 
-pub(crate) fn init_logging(v: u8) {
+/// Format, timestamps and destination of tino's own logs.
+pub(crate) struct LogOptions {
+    pub format: LogFormat,
+    pub timestamps: Option<LogTimestamps>,
+    pub writer: BoxMakeWriter,
+}
+
+impl Default for LogOptions {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::Text,
+            timestamps: None,
+            writer: BoxMakeWriter::new(io::stderr),
+        }
+    }
+}
+
+impl LogOptions {
+    fn from_cli(cli: &Cli) -> Result<Self> {
+        let writer = match (&cli.log_file, cli.log_fd) {
+            (Some(_), Some(_)) => bail!("--log-file and --log-fd are mutually exclusive"),
+            (Some(path), None) => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("open log file {}", path.display()))?;
+                BoxMakeWriter::new(Arc::new(file))
+            }
+            (None, Some(fd)) => BoxMakeWriter::new(Arc::new(log_fd_file(fd)?)),
+            (None, None) => BoxMakeWriter::new(io::stderr),
+        };
+        Ok(Self {
+            format: cli.log_format,
+            timestamps: cli.log_timestamps,
+            writer,
+        })
+    }
+}
+
+/// Takes over an inherited descriptor for logging. Descriptors above 2 are
+/// marked close-on-exec so supervised processes do not inherit them.
+fn log_fd_file(fd: RawFd) -> Result<File> {
+    // SAFETY: F_GETFD only queries descriptor flags.
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+    if fd < 0 || flags == -1 {
+        bail!("--log-fd {fd} is not an open file descriptor");
+    }
+    // SAFETY: F_SETFD only updates descriptor flags of a descriptor we verified is open.
+    if fd > 2 && unsafe { libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC) } == -1 {
+        return Err(io::Error::last_os_error()).context("set close-on-exec on --log-fd");
+    }
+    // SAFETY: the descriptor is open and tino takes sole ownership of it from here on.
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+/// Current wall-clock time in the RFC 3339 form used by `--log-timestamps`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn rfc3339_now() -> String {
+    let mut out = String::new();
+    let _ = SystemTime.format_time(&mut Writer::new(&mut out));
+    out
+}
+
+/// Timer selected by `--log-timestamps`.
+pub(crate) enum LogTimer {
+    Rfc3339,
+    /// Seconds since logging was initialised, unpadded so it stays one token.
+    Monotonic(Instant),
+}
+
+impl FormatTime for LogTimer {
+    fn format_time(&self, w: &mut Writer<'_>) -> std::fmt::Result {
+        match self {
+            LogTimer::Rfc3339 => SystemTime.format_time(w),
+            LogTimer::Monotonic(start) => write!(w, "{:.6}s", start.elapsed().as_secs_f64()),
+        }
+    }
+}
+
+pub(crate) fn init_logging(v: u8, options: LogOptions) {
     let lvl = match v {
         0 => "info",
         1 => "debug",
@@ -145,13 +260,35 @@ pub(crate) fn init_logging(v: u8) {
     };
     LOGGER.get_or_init(move || {
         let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(lvl));
-        if let Err(e) = fmt::Subscriber::builder()
+        let timer = options.timestamps.map(|style| match style {
+            LogTimestamps::Rfc3339 => LogTimer::Rfc3339,
+            LogTimestamps::Monotonic => LogTimer::Monotonic(Instant::now()),
+        });
+        let builder = fmt::Subscriber::builder()
             .with_env_filter(filter)
             .with_target(false)
-            .without_time()
-            .with_writer(io::stderr)
-            .try_init()
-        {
+            .with_writer(options.writer);
+        // Timer and format both change the builder's type, hence one arm per combination.
+        let result = match (options.format, timer) {
+            (LogFormat::Logfmt, timer) => builder.event_format(logfmt::Logfmt { timer }).try_init(),
+            (LogFormat::Text, None) => builder.without_time().try_init(),
+            (LogFormat::Text, Some(timer)) => builder.with_timer(timer).try_init(),
+            (LogFormat::Json, None) => builder
+                .without_time()
+                .json()
+                .flatten_event(true)
+                .with_current_span(false)
+                .with_span_list(false)
+                .try_init(),
+            (LogFormat::Json, Some(timer)) => builder
+                .with_timer(timer)
+                .json()
+                .flatten_event(true)
+                .with_current_span(false)
+                .with_span_list(false)
+                .try_init(),
+        };
+        if let Err(e) = result {
             warn!(
                 error = %e,
                 "logging initialization failed; continuing with existing dispatcher"
@@ -161,18 +298,29 @@ pub(crate) fn init_logging(v: u8) {
 }
 
 #[cfg(target_os = "linux")]
-fn run_impl(cli: Cli, expect_zero: HashSet<u8>) -> Result<i32> {
-    unix::run_impl(cli, expect_zero)
+fn run_ctl(args: &CtlArgs) -> Result<i32> {
+    unix::run_ctl(args)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn run_ctl(args: &CtlArgs) -> Result<i32> {
+    stub::run_ctl(args)
+}
+
+#[cfg(target_os = "linux")]
+fn run_impl(cli: Cli, exit_map: ExitCodeMap) -> Result<i32> {
+    unix::run_impl(cli, exit_map)
 }
 
 #[cfg(not(target_os = "linux"))]
-fn run_impl(cli: Cli, expect_zero: HashSet<u8>) -> Result<i32> {
-    stub::run_impl(cli, expect_zero)
+fn run_impl(cli: Cli, exit_map: ExitCodeMap) -> Result<i32> {
+    stub::run_impl(cli, exit_map)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cli::{ExitPolicy, LogFormat, OutputMode, RestartPolicy};
 
     #[test]
     fn empty_boolean_override_is_rejected() {
@@ -184,22 +332,66 @@ mod tests {
             subreaper: false,
             pdeath: None,
             verbosity: 0,
+            log_format: LogFormat::Text,
+            log_timestamps: None,
+            log_file: None,
+            log_fd: None,
             warn_on_reap: false,
             pgroup_kill: false,
+            kill_scope: None,
             remap_exit: Vec::new(),
+            map_exit: Vec::new(),
+            reraise: false,
             grace_ms: 500,
+            stop_sequence: Vec::new(),
+            stop_signal: None,
+            stop_command: None,
+            stop_delay: None,
+            readiness_file: None,
+            interrupt_kill_count: 0,
+            interrupt_kill_window: std::time::Duration::from_secs(5),
+            straggler_sequence: Vec::new(),
             license: false,
+            config: None,
+            sidecars: Vec::new(),
+            exit_policy: ExitPolicy::Main,
+            restart: RestartPolicy::No,
+            restart_delay_ms: 100,
+            restart_max_delay_ms: 30_000,
+            max_restarts: None,
+            restart_reset_ms: 10_000,
+            user: None,
+            no_new_privs: false,
+            drop_caps: Vec::new(),
+            keep_caps: Vec::new(),
+            output: OutputMode::Inherit,
+            output_timestamps: false,
+            stdout_file: None,
+            stderr_file: None,
+            output_max_size: None,
+            output_keep: 5,
+            control_socket: None,
+            rewrites: Vec::new(),
+            forward: Vec::new(),
+            no_forward: Vec::new(),
             subreaper_env: None,
             pgroup_env: None,
             verbosity_env: None,
             cmd: vec!["/bin/true".into()],
+            command: None,
         }
     }
 
     #[test]
     fn init_logging_is_idempotent() {
-        init_logging(0);
-        init_logging(1);
+        init_logging(0, LogOptions::default());
+        init_logging(
+            1,
+            LogOptions {
+                format: LogFormat::Json,
+                ..LogOptions::default()
+            },
+        );
     }
 
     #[test]
@@ -1,39 +1,82 @@
-use crate::cli::Cli;
+use crate::cli::{Cli, CtlArgs};
+use crate::exit_code::ExitCodeMap;
 use anyhow::{Result, bail};
-use std::collections::HashSet;
 
-pub(super) fn run_impl(_cli: Cli, _expect_zero: HashSet<u8>) -> Result<i32> {
+pub(super) fn run_impl(_cli: Cli, _exit_map: ExitCodeMap) -> Result<i32> {
     bail!(
         "tino supports Unix-like targets only. Build and test inside a Linux container or VM \
          (see README requirements)."
     );
 }
 
+pub(super) fn run_ctl(_args: &CtlArgs) -> Result<i32> {
+    bail!("tino ctl supports Unix-like targets only.");
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cli::{ExitPolicy, LogFormat, OutputMode, RestartPolicy};
 
     fn base_cli() -> Cli {
         Cli {
             subreaper: false,
             pdeath: None,
             verbosity: 0,
+            log_format: LogFormat::Text,
+            log_timestamps: None,
+            log_file: None,
+            log_fd: None,
             warn_on_reap: false,
             pgroup_kill: false,
+            kill_scope: None,
             remap_exit: Vec::new(),
+            map_exit: Vec::new(),
+            reraise: false,
             grace_ms: 500,
+            stop_sequence: Vec::new(),
+            stop_signal: None,
+            stop_command: None,
+            stop_delay: None,
+            readiness_file: None,
+            interrupt_kill_count: 0,
+            interrupt_kill_window: std::time::Duration::from_secs(5),
+            straggler_sequence: Vec::new(),
             license: false,
+            config: None,
+            sidecars: Vec::new(),
+            exit_policy: ExitPolicy::Main,
+            restart: RestartPolicy::No,
+            restart_delay_ms: 100,
+            restart_max_delay_ms: 30_000,
+            max_restarts: None,
+            restart_reset_ms: 10_000,
+            user: None,
+            no_new_privs: false,
+            drop_caps: Vec::new(),
+            keep_caps: Vec::new(),
+            output: OutputMode::Inherit,
+            output_timestamps: false,
+            stdout_file: None,
+            stderr_file: None,
+            output_max_size: None,
+            output_keep: 5,
+            control_socket: None,
+            rewrites: Vec::new(),
+            forward: Vec::new(),
+            no_forward: Vec::new(),
             subreaper_env: None,
             pgroup_env: None,
             verbosity_env: None,
             cmd: vec!["/bin/true".into()],
+            command: None,
         }
     }
 
     #[test]
     fn stub_reports_linux_requirement() {
         let cli = base_cli();
-        let err = run_impl(cli, HashSet::new()).unwrap_err();
+        let err = run_impl(cli, ExitCodeMap::default()).unwrap_err();
         let message = format!("{err}");
         assert!(
             message.contains("supports Unix-like targets"),
@@ -4,12 +4,22 @@ use libc::{_exit, PR_SET_CHILD_SUBREAPER, PR_SET_PDEATHSIG};
 use nix::{
     errno::Errno,
     sys::signal::SigSet,
-    unistd::{ForkResult, Pid, execvp, fork, getpgid, setpgid},
+    unistd::{ForkResult, Pid, execvp, execvpe, fork, getpgid, setpgid},
+};
+use std::{
+    env,
+    ffi::{CStr, CString},
+    fmt, fs,
+    io::{BufRead, BufReader, Read},
+    os::fd::{AsRawFd, OwnedFd, RawFd},
+    path::{Path, PathBuf},
 };
-use std::ffi::CString;
 use tracing::warn;
 
+use super::output::cloexec_pipe;
+use super::privileges::Privileges;
 use super::signals;
+use super::user::Credentials;
 
 #[derive(Default)]
 pub(super) struct PrctlOutcome {
@@ -28,7 +38,7 @@ pub(super) fn configure_prctl(cli: &Cli) -> Result<PrctlOutcome> {
         })?;
         // SAFETY: `sig` is a valid signal number and `prctl` is called with documented parameters.
         unsafe {
-            if libc::prctl(PR_SET_PDEATHSIG, sig as i32) == -1 {
+            if libc::prctl(PR_SET_PDEATHSIG, sig.as_raw()) == -1 {
                 bail!("prctl P_DEATHSIG: {}", Errno::last());
             }
         }
@@ -88,50 +98,238 @@ fn child_write(bytes: &[u8]) {
     }
 }
 
-fn child_write_errno(errno: Errno) {
-    let mut value = errno as i32;
-    let mut buf = [0u8; 12];
-    let mut idx = buf.len();
-    if value == 0 {
-        idx -= 1;
-        buf[idx] = b'0';
-    } else {
-        while value > 0 {
-            let digit = (value % 10) as u8;
-            idx -= 1;
-            buf[idx] = b'0' + digit;
-            value /= 10;
+/// The step between fork and exec that failed, sent ahead of the errno.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub(super) enum ExecStage {
+    Redirect = 1,
+    SignalMask,
+    KeepCaps,
+    DropCaps,
+    SwitchUser,
+    AmbientCaps,
+    NoNewPrivs,
+    Exec,
+}
+
+impl ExecStage {
+    const ALL: [ExecStage; 8] = [
+        ExecStage::Redirect,
+        ExecStage::SignalMask,
+        ExecStage::KeepCaps,
+        ExecStage::DropCaps,
+        ExecStage::SwitchUser,
+        ExecStage::AmbientCaps,
+        ExecStage::NoNewPrivs,
+        ExecStage::Exec,
+    ];
+
+    fn from_raw(raw: u8) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|stage| *stage as u8 == raw)
+            .unwrap_or(ExecStage::Exec)
+    }
+
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            ExecStage::Redirect => "redirecting output",
+            ExecStage::SignalMask => "restoring the signal mask",
+            ExecStage::KeepCaps => "keeping capabilities across the user switch",
+            ExecStage::DropCaps => "dropping bounding capabilities",
+            ExecStage::SwitchUser => "switching user",
+            ExecStage::AmbientCaps => "raising ambient capabilities",
+            ExecStage::NoNewPrivs => "setting no_new_privs",
+            ExecStage::Exec => "exec",
+        }
+    }
+}
+
+/// Why a freshly forked child never reached the command.
+#[derive(Debug)]
+pub(super) struct ExecFailure {
+    pub program: String,
+    pub stage: ExecStage,
+    pub errno: Errno,
+    /// The `#!` interpreter of a script that exists but could not be started.
+    pub interpreter: Option<String>,
+}
+
+impl ExecFailure {
+    fn new(program: &CStr, stage: ExecStage, errno: Errno) -> Self {
+        let program = program.to_string_lossy().into_owned();
+        let interpreter = (stage == ExecStage::Exec && errno == Errno::ENOENT)
+            .then(|| find_program(&program))
+            .flatten()
+            .and_then(|path| shebang_interpreter(&path));
+        Self {
+            program,
+            stage,
+            errno,
+            interpreter,
         }
     }
-    child_write(&buf[idx..]);
+
+    /// 127 when the command does not exist and 126 when it cannot be run, as
+    /// shells report them.
+    pub(super) fn exit_code(&self) -> i32 {
+        if self.stage == ExecStage::Exec
+            && self.errno == Errno::ENOENT
+            && self.interpreter.is_none()
+        {
+            127
+        } else {
+            126
+        }
+    }
+
+    pub(super) fn reason(&self) -> String {
+        if self.stage != ExecStage::Exec {
+            let error = self.errno.desc().to_lowercase();
+            return format!("{} failed: {error}", self.stage.as_str());
+        }
+        if let Some(interpreter) = &self.interpreter {
+            return format!("bad interpreter {interpreter}");
+        }
+        match self.errno {
+            Errno::ENOENT if !self.program.contains('/') => "not found in PATH".into(),
+            Errno::ENOENT => "no such file".into(),
+            Errno::EACCES => "permission denied".into(),
+            Errno::ENOEXEC => "exec format error".into(),
+            errno => errno.desc().to_lowercase(),
+        }
+    }
+}
+
+impl fmt::Display for ExecFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot execute {}: {}", self.program, self.reason())
+    }
 }
 
-fn report_exec_failure(program: &CString, errno: Errno) -> ! {
-    child_write(b"tino: execvp failed for ");
-    child_write(program.as_bytes());
-    child_write(b" (errno ");
-    child_write_errno(errno);
-    child_write(b")\n");
-    unsafe { _exit(127) }
+impl std::error::Error for ExecFailure {}
+
+/// Resolves `program` the way `execvp` does, for diagnostics only.
+fn find_program(program: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        return Some(PathBuf::from(program)).filter(|path| path.is_file());
+    }
+    let search = env::var_os("PATH").unwrap_or_else(|| "/usr/local/bin:/usr/bin:/bin".into());
+    env::split_paths(&search)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+fn shebang_interpreter(script: &Path) -> Option<String> {
+    let mut first = String::new();
+    BufReader::new(fs::File::open(script).ok()?)
+        .take(256)
+        .read_line(&mut first)
+        .ok()?;
+    let interpreter = first.strip_prefix("#!")?.split_whitespace().next()?;
+    Some(interpreter.to_string())
+}
+
+fn report_exec_failure(status_fd: RawFd, stage: ExecStage, errno: Errno) -> ! {
+    let errno = (errno as i32).to_ne_bytes();
+    let bytes = [stage as u8, errno[0], errno[1], errno[2], errno[3]];
+    // SAFETY: write(2) and _exit(2) are async-signal-safe.
+    unsafe {
+        let _ = libc::write(
+            status_fd,
+            bytes.as_ptr() as *const libc::c_void,
+            bytes.len(),
+        );
+        _exit(127)
+    }
 }
 
-pub(super) fn spawn_child(block: SigSet, cmd_c: &CString, argv_c: &[CString]) -> Result<Pid> {
+/// Blocks until the child has exec'd (EOF) or reported a failure.
+fn read_exec_status(status: OwnedFd) -> Option<(ExecStage, Errno)> {
+    let mut buf = [0u8; 5];
+    let mut filled = 0;
+    while filled < buf.len() {
+        // SAFETY: the destination range lies within `buf`.
+        let n = unsafe {
+            libc::read(
+                status.as_raw_fd(),
+                buf[filled..].as_mut_ptr() as *mut libc::c_void,
+                buf.len() - filled,
+            )
+        };
+        match n {
+            0 => break,
+            n if n > 0 => filled += n as usize,
+            _ if Errno::last() == Errno::EINTR => continue,
+            _ => break,
+        }
+    }
+    let [stage, errno @ ..] = buf;
+    (filled == buf.len()).then(|| {
+        (
+            ExecStage::from_raw(stage),
+            Errno::from_raw(i32::from_ne_bytes(errno)),
+        )
+    })
+}
+
+/// Forks and execs the command. `stdio` replaces the child's stdout and stderr;
+/// `privileges` are applied, and `HOME`/`USER` follow `--user`, before exec.
+/// Returns an [`ExecFailure`] error, with the child already reaped, when
+/// any of that or `execvp` fails.
+pub(super) fn spawn_child(
+    block: SigSet,
+    cmd_c: &CString,
+    argv_c: &[CString],
+    stdio: Option<[RawFd; 2]>,
+    privileges: &Privileges,
+) -> Result<Pid> {
+    let env = privileges
+        .user
+        .as_ref()
+        .map(Credentials::environment)
+        .transpose()?;
+    // EOF on the read end means `execvp` succeeded; otherwise the child sends
+    // the failed stage and its errno before exiting.
+    let (status_read, status_write) = cloexec_pipe()?;
     // SAFETY: the forked child only performs async-signal-safe operations before exec or exit.
     match unsafe { fork()? } {
         ForkResult::Child => {
+            let status_fd = status_write.as_raw_fd();
             if setpgid(Pid::from_raw(0), Pid::from_raw(0)).is_err() {
                 child_write(b"tino: failed to establish child process group\n");
             }
-            if block.thread_unblock().is_err() {
-                child_write(b"tino: failed to restore signal mask in child\n");
-                unsafe { _exit(1) }
+            for (fd, target) in stdio.into_iter().flatten().zip([1, 2]) {
+                // SAFETY: dup2 is async-signal-safe; both descriptors are valid.
+                if unsafe { libc::dup2(fd, target) } == -1 {
+                    report_exec_failure(status_fd, ExecStage::Redirect, Errno::last());
+                }
+            }
+            if let Err(errno) = block.thread_unblock() {
+                report_exec_failure(status_fd, ExecStage::SignalMask, errno);
+            }
+            if let Err((stage, errno)) = privileges.apply() {
+                report_exec_failure(status_fd, stage, errno);
             }
-            match execvp(cmd_c, argv_c) {
+            let exec = match &env {
+                Some(env) => execvpe(cmd_c, argv_c, env),
+                None => execvp(cmd_c, argv_c),
+            };
+            match exec {
                 Ok(_) => unsafe { _exit(127) },
-                Err(err) => report_exec_failure(cmd_c, err),
+                Err(err) => report_exec_failure(status_fd, ExecStage::Exec, err),
             }
         }
-        ForkResult::Parent { child } => Ok(child),
+        ForkResult::Parent { child } => {
+            drop(status_write);
+            let Some((stage, errno)) = read_exec_status(status_read) else {
+                return Ok(child);
+            };
+            let mut status = 0;
+            // SAFETY: `status` is a valid out-pointer; `child` is our own exiting child.
+            unsafe { libc::waitpid(child.as_raw(), &mut status, 0) };
+            Err(ExecFailure::new(cmd_c, stage, errno).into())
+        }
     }
 }
 
@@ -165,6 +363,7 @@ pub(super) fn manage_process_group(requested: bool, child_pid: Pid) -> bool {
 #[cfg(all(test, target_os = "linux"))]
 mod tests {
     use super::*;
+    use crate::cli::{ExitPolicy, LogFormat, OutputMode, RestartPolicy};
     use std::io;
 
     struct PrctlStateGuard {
@@ -217,15 +416,53 @@ mod tests {
             subreaper: false,
             pdeath: None,
             verbosity: 0,
+            log_format: LogFormat::Text,
+            log_timestamps: None,
+            log_file: None,
+            log_fd: None,
             warn_on_reap: false,
             pgroup_kill: false,
+            kill_scope: None,
             remap_exit: Vec::new(),
+            map_exit: Vec::new(),
+            reraise: false,
             grace_ms: 500,
+            stop_sequence: Vec::new(),
+            stop_signal: None,
+            stop_command: None,
+            stop_delay: None,
+            readiness_file: None,
+            interrupt_kill_count: 0,
+            interrupt_kill_window: std::time::Duration::from_secs(5),
+            straggler_sequence: Vec::new(),
             license: false,
+            config: None,
+            sidecars: Vec::new(),
+            exit_policy: ExitPolicy::Main,
+            restart: RestartPolicy::No,
+            restart_delay_ms: 100,
+            restart_max_delay_ms: 30_000,
+            max_restarts: None,
+            restart_reset_ms: 10_000,
+            user: None,
+            no_new_privs: false,
+            drop_caps: Vec::new(),
+            keep_caps: Vec::new(),
+            output: OutputMode::Inherit,
+            output_timestamps: false,
+            stdout_file: None,
+            stderr_file: None,
+            output_max_size: None,
+            output_keep: 5,
+            control_socket: None,
+            rewrites: Vec::new(),
+            forward: Vec::new(),
+            no_forward: Vec::new(),
             subreaper_env: None,
             pgroup_env: None,
             verbosity_env: None,
             cmd: vec!["/bin/true".into()],
+            command: None,
         }
     }
 
@@ -0,0 +1,200 @@
+use crate::control::{Request, Response};
+use anyhow::{Context, Result, bail};
+use std::{
+    fs,
+    io::{self, ErrorKind, Read, Write},
+    os::{
+        fd::{AsFd, BorrowedFd},
+        unix::{
+            fs::FileTypeExt,
+            net::{UnixListener, UnixStream},
+        },
+    },
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::{debug, warn};
+
+const MAX_CLIENTS: usize = 16;
+const MAX_REQUEST_BYTES: usize = 4096;
+const REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Non-blocking control socket serviced from the supervisor's poll loop.
+pub(super) struct ControlServer {
+    listener: UnixListener,
+    path: PathBuf,
+    clients: Vec<Client>,
+}
+
+struct Client {
+    stream: UnixStream,
+    buf: Vec<u8>,
+}
+
+/// A complete request read from a client, answered through [`PendingRequest::reply`].
+pub(super) struct PendingRequest {
+    stream: UnixStream,
+    pub request: std::result::Result<Request, String>,
+}
+
+impl ControlServer {
+    pub(super) fn bind(path: &Path) -> Result<Self> {
+        match fs::symlink_metadata(path) {
+            // Only a socket nobody listens on is stale; never steal a live one.
+            Ok(meta) if meta.file_type().is_socket() => match UnixStream::connect(path) {
+                Ok(_) => bail!("control socket in use: {}", path.display()),
+                Err(err) if err.kind() == ErrorKind::ConnectionRefused => {
+                    fs::remove_file(path).with_context(|| {
+                        format!("remove stale control socket {}", path.display())
+                    })?;
+                }
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("control socket in use: {}", path.display()));
+                }
+            },
+            Ok(_) => bail!("control socket path {} exists", path.display()),
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(err).with_context(|| format!("stat {}", path.display()));
+            }
+        }
+        let listener = UnixListener::bind(path)
+            .with_context(|| format!("bind control socket {}", path.display()))?;
+        listener
+            .set_nonblocking(true)
+            .context("control socket nonblocking")?;
+        Ok(Self {
+            listener,
+            path: path.to_path_buf(),
+            clients: Vec::new(),
+        })
+    }
+
+    /// Descriptors to poll: the listener first, then one per connected client.
+    pub(super) fn poll_fds(&self) -> Vec<BorrowedFd<'_>> {
+        std::iter::once(self.listener.as_fd())
+            .chain(self.clients.iter().map(|c| c.stream.as_fd()))
+            .collect()
+    }
+
+    /// Accepts new connections and reads from clients flagged readable in
+    /// `ready` (aligned with [`ControlServer::poll_fds`]).
+    pub(super) fn service(&mut self, ready: &[bool]) -> Vec<PendingRequest> {
+        let mut pending = Vec::new();
+        let mut idx = self.clients.len();
+        while idx > 0 {
+            idx -= 1;
+            if !ready.get(idx + 1).copied().unwrap_or(false) {
+                continue;
+            }
+            match self.clients[idx].read_available() {
+                Ok(Some(request)) => {
+                    let client = self.clients.swap_remove(idx);
+                    pending.push(PendingRequest {
+                        stream: client.stream,
+                        request,
+                    });
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    debug!(error = %err, "dropping control client");
+                    self.clients.swap_remove(idx);
+                }
+            }
+        }
+        if ready.first().copied().unwrap_or(false) {
+            self.accept_clients();
+        }
+        pending
+    }
+
+    fn accept_clients(&mut self) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(err) = stream.set_nonblocking(true) {
+                        warn!(error = %err, "control client setup failed");
+                        continue;
+                    }
+                    if self.clients.len() >= MAX_CLIENTS {
+                        debug!("too many control clients; dropping the oldest");
+                        self.clients.remove(0);
+                    }
+                    self.clients.push(Client {
+                        stream,
+                        buf: Vec::new(),
+                    });
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    warn!(error = %err, "control socket accept failed");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl Client {
+    /// Returns a request once a full line (or EOF after data) has arrived.
+    fn read_available(&mut self) -> io::Result<Option<std::result::Result<Request, String>>> {
+        let mut chunk = [0u8; 512];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => {
+                    if self.buf.is_empty() {
+                        return Err(ErrorKind::UnexpectedEof.into());
+                    }
+                    return Ok(Some(self.take_request()));
+                }
+                Ok(n) => {
+                    self.buf.extend_from_slice(&chunk[..n]);
+                    if self.buf.contains(&b'\n') {
+                        return Ok(Some(self.take_request()));
+                    }
+                    if self.buf.len() > MAX_REQUEST_BYTES {
+                        return Ok(Some(Err("request too long".into())));
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(None),
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn take_request(&mut self) -> std::result::Result<Request, String> {
+        let end = self
+            .buf
+            .iter()
+            .position(|&b| b == b'\n')
+            .unwrap_or(self.buf.len());
+        let line = std::str::from_utf8(&self.buf[..end]).map_err(|_| "request is not UTF-8")?;
+        Request::parse(line.trim())
+    }
+}
+
+impl PendingRequest {
+    pub(super) fn reply(self, response: &Response) {
+        let mut stream = self.stream;
+        let result = serde_json::to_vec(response)
+            .map_err(io::Error::other)
+            .and_then(|mut body| {
+                body.push(b'\n');
+                stream.set_nonblocking(false)?;
+                stream.set_write_timeout(Some(REPLY_TIMEOUT))?;
+                stream.write_all(&body)
+            });
+        if let Err(err) = result {
+            debug!(error = %err, "control reply failed");
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crate::cli::CtlArgs;
+use crate::control::Response;
+use anyhow::{Context, Result};
+use std::{
+    io::{Read, Write},
+    net::Shutdown,
+    os::unix::net::UnixStream,
+    time::Duration,
+};
+
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Client side of `tino ctl`: sends one request and prints the reply.
+pub(super) fn run_ctl(args: &CtlArgs) -> Result<i32> {
+    let request = args.action.to_request();
+    let mut stream = UnixStream::connect(&args.socket)
+        .with_context(|| format!("connect control socket {}", args.socket.display()))?;
+    stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+    stream
+        .write_all(format!("{request}\n").as_bytes())
+        .context("send control request")?;
+    stream.shutdown(Shutdown::Write)?;
+
+    let mut reply = String::new();
+    stream
+        .read_to_string(&mut reply)
+        .context("read control reply")?;
+    let response: Response = serde_json::from_str(reply.trim()).context("decode control reply")?;
+
+    if args.json {
+        println!("{}", reply.trim());
+    } else if let Some(error) = &response.error {
+        eprintln!("error: {error}");
+    } else if let Some(status) = &response.status {
+        print!("{}", status.render());
+    } else {
+        println!("ok");
+    }
+    Ok(if response.ok { 0 } else { 1 })
+}
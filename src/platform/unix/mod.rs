@@ -1,225 +1,1104 @@
-use crate::cli::Cli;
+use crate::cli::{
+    Cli, CtlArgs, ExitPolicy, KillScope, MAIN_PROCESS_NAME, OutputMode, RestartPolicy,
+};
+use crate::control::{ExitStatus, ProcessStatus, Request, Response, Status};
+use crate::exit_code::ExitCodeMap;
 use anyhow::{Context, Result, bail};
 use nix::{
     errno::Errno,
     poll::{PollFd, PollFlags, PollTimeout, poll},
-    sys::{
-        signal::{SIGCHLD, SIGINT, SIGKILL, SIGQUIT, SIGTERM, Signal},
-        signalfd::SignalFd,
-        wait::{WaitPidFlag, WaitStatus, waitpid},
-    },
+    sys::{signal::SigSet, signalfd::SignalFd},
     unistd::Pid,
 };
 use std::{
-    collections::HashSet,
     os::fd::AsFd,
-    thread,
     time::{Duration, Instant},
 };
-use tracing::{debug, info, warn};
+use tracing::{debug, error, info, warn};
 
 mod child;
+mod control;
+mod ctl;
+mod output;
+mod privileges;
+mod process;
+mod proctree;
+mod rotate;
 mod signals;
+mod user;
+
+use child::{ExecFailure, configure_prctl, start_session};
+use control::ControlServer;
+use output::{ChildPipes, OutputRelay};
+use privileges::{Privileges, log_capabilities};
+use process::{Process, ProcessTable, Termination, signal_stragglers};
+use proctree::descendants;
+use rotate::Rotation;
+use signals::{
+    Sig, SignalRewrites, Stage, default_stop_stages, forwarded_signals, reraise,
+    setup_signal_delivery, signal_by_name, stop_stages,
+};
 
-use child::{configure_prctl, manage_process_group, prepare_command, spawn_child, start_session};
-use signals::{send_signal, setup_signal_delivery};
+/// Serialises unit tests that spawn children or reap with `waitpid(-1)`.
+#[cfg(test)]
+fn child_test_lock() -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+pub(super) fn run_ctl(args: &CtlArgs) -> Result<i32> {
+    ctl::run_ctl(args)
+}
 
-pub(super) fn run_impl(cli: Cli, expect_zero: HashSet<u8>) -> Result<i32> {
+pub(super) fn run_impl(cli: Cli, exit_map: ExitCodeMap) -> Result<i32> {
     configure_prctl(&cli)?;
-    let (block, mut signal_fd) = setup_signal_delivery()?;
+    let rewrites = SignalRewrites::resolve(&cli.rewrites)?;
+    let mut forwarded = forwarded_signals(&cli.forward, &cli.no_forward)?;
+    // With output files, SIGHUP rotates them and reaches children only when
+    // `--forward` names it explicitly.
+    let to_file = cli.stdout_file.is_some() || cli.stderr_file.is_some();
+    let consume_hup = to_file && !cli.forward.iter().any(|name| name == "SIGHUP");
+    if consume_hup {
+        forwarded.retain(|&sig| sig != Sig::HUP);
+    }
+    for rewrite in &cli.rewrites {
+        if signal_by_name(&rewrite.from).is_some_and(|sig| !forwarded.contains(&sig)) {
+            warn!(
+                "--rewrite {} has no effect; the signal is not forwarded",
+                rewrite.from
+            );
+        }
+    }
+    debug!("forwarding {:?}", forwarded);
+    let intercepted: Vec<Sig> = forwarded
+        .iter()
+        .copied()
+        .chain(consume_hup.then_some(Sig::HUP))
+        .collect();
+    let (block, signal_fd) = setup_signal_delivery(&intercepted)?;
     start_session()?;
+    let control = cli
+        .control_socket
+        .as_deref()
+        .map(ControlServer::bind)
+        .transpose()?;
 
-    let (cmd_c, argv_c) =
-        prepare_command(&cli.cmd).with_context(|| format!("prepare command {:?}", cli.cmd))?;
-    let child_pid = spawn_child(block, &cmd_c, &argv_c)
-        .with_context(|| format!("spawn child {:?}", cli.cmd))?;
-    let use_pgroup = manage_process_group(cli.pgroup_kill, child_pid);
+    let mut table = ProcessTable::new(Process::new(MAIN_PROCESS_NAME, &cli.cmd)?);
+    for spec in &cli.sidecars {
+        table.push(Process::new(&spec.name, &spec.cmd)?);
+    }
+    // Writing to a file needs the output captured; plain is the natural default.
+    let output_mode = match cli.output {
+        OutputMode::Inherit if to_file => OutputMode::Plain,
+        mode => mode,
+    };
+    if cli.output_timestamps && output_mode == OutputMode::Inherit {
+        warn!("--output-timestamps has no effect with --output inherit");
+    }
+    if !to_file && cli.output_max_size.is_some() {
+        warn!("--output-max-size has no effect without --stdout-file or --stderr-file");
+    }
+    let mut output = OutputRelay::new(output_mode, cli.output_timestamps);
+    if let Some(output) = output.as_mut() {
+        let rotation = Rotation {
+            max_size: cli.output_max_size,
+            keep: cli.output_keep,
+        };
+        output.open_files(
+            cli.stdout_file.as_deref(),
+            cli.stderr_file.as_deref(),
+            rotation,
+        )?;
+    }
+
+    let grace = Duration::from_millis(cli.grace_ms);
+    let stop = stop_stages(&cli.stop_sequence, grace)?;
+    let stragglers = stop_stages(&cli.straggler_sequence, grace)?;
 
-    supervise_child(&cli, &expect_zero, child_pid, use_pgroup, &mut signal_fd)
+    let mut supervisor = Supervisor::new(&cli, &exit_map, block, table);
+    supervisor.stop_stages = stop;
+    supervisor.stop_signal = cli
+        .stop_signal
+        .as_deref()
+        .map(|name| signal_by_name(name).with_context(|| format!("unsupported signal {name}")))
+        .transpose()?;
+    supervisor.stop_command = cli
+        .stop_command
+        .as_ref()
+        .map(|argv| Process::new("stop-command", &argv.0))
+        .transpose()?;
+    supervisor.privileges = Privileges::from_cli(&cli)?;
+    supervisor.straggler_stages = stragglers;
+    supervisor.control = control;
+    supervisor.rewrites = rewrites;
+    supervisor.output = output;
+    supervisor.consume_hup = consume_hup;
+    supervisor.start()?;
+    supervisor.run(&signal_fd)
 }
 
-fn supervise_child(
-    cli: &Cli,
-    expect_zero: &HashSet<u8>,
-    child_pid: Pid,
-    use_pgroup: bool,
-    signal_fd: &mut SignalFd,
-) -> Result<i32> {
-    let mut main_exit: Option<i32> = None;
-    let mut shutdown_deadline: Option<Instant> = None;
-    let mut sigkill_sent = false;
-    let mut fds = [PollFd::new(signal_fd.as_fd(), PollFlags::POLLIN)];
+struct Supervisor<'a> {
+    cli: &'a Cli,
+    exit_map: &'a ExitCodeMap,
+    block: SigSet,
+    table: ProcessTable,
+    /// Termination that decided tino's exit status, once settled.
+    exit_status: Option<Termination>,
+    /// A termination signal was received; exited processes are no longer restarted.
+    stopping: bool,
+    /// Escalation for supervised processes once shutdown starts.
+    stop_stages: Vec<Stage>,
+    /// Escalation for whatever outlives the exit decision.
+    straggler_stages: Vec<Stage>,
+    /// Index of the next `stop_stages` entry to send.
+    next_stage: usize,
+    /// When to send `stop_stages[next_stage]`.
+    shutdown_deadline: Option<Instant>,
+    /// `--stop-signal`, sent instead of a received termination signal.
+    stop_signal: Option<Sig>,
+    /// `--stop-command`, run instead of forwarding a termination signal.
+    stop_command: Option<Process>,
+    /// `--user` and capability options, applied to every spawned process.
+    privileges: Privileges,
+    /// A termination signal held back by `--stop-delay`, and when to act on it.
+    delayed_stop: Option<(Instant, Sig)>,
+    /// `--stop-delay` applies to the first termination signal only.
+    stop_delay_used: bool,
+    /// When recent SIGINTs arrived, for `--interrupt-kill-count`.
+    interrupts: Vec<Instant>,
+    control: Option<ControlServer>,
+    rewrites: SignalRewrites,
+    output: Option<OutputRelay>,
+    /// SIGHUP only rotates output files; see `run_impl`.
+    consume_hup: bool,
+    started: Instant,
+}
 
-    loop {
-        let poll_timeout = match (shutdown_deadline, sigkill_sent, main_exit.is_some()) {
-            (Some(deadline), false, false) => {
-                let remaining = deadline.saturating_duration_since(Instant::now());
+/// Readiness of the poll loop's sources, each aligned with its `poll_fds`.
+struct Ready {
+    signals: bool,
+    /// A supervised process's pidfd reported its exit.
+    exited: bool,
+    control: Vec<bool>,
+    output: Vec<bool>,
+}
+
+impl<'a> Supervisor<'a> {
+    fn new(cli: &'a Cli, exit_map: &'a ExitCodeMap, block: SigSet, table: ProcessTable) -> Self {
+        Self {
+            cli,
+            exit_map,
+            block,
+            table,
+            exit_status: None,
+            stopping: false,
+            stop_stages: Vec::new(),
+            straggler_stages: Vec::new(),
+            next_stage: 0,
+            shutdown_deadline: None,
+            stop_signal: None,
+            stop_command: None,
+            privileges: Privileges::default(),
+            delayed_stop: None,
+            stop_delay_used: false,
+            interrupts: Vec::new(),
+            control: None,
+            rewrites: SignalRewrites::default(),
+            output: None,
+            consume_hup: false,
+            started: Instant::now(),
+        }
+    }
+
+    fn start(&mut self) -> Result<()> {
+        for idx in 0..self.table.len() {
+            if self.exit_status.is_some() {
+                break;
+            }
+            let Some(pid) = self.spawn(idx)? else {
+                continue;
+            };
+            debug!(
+                process = %self.table.get(idx).name,
+                pid = pid.as_raw(),
+                phase = "startup",
+                "started process"
+            );
+        }
+        Ok(())
+    }
+
+    fn next_wakeup(&self) -> Option<Instant> {
+        [
+            self.shutdown_deadline,
+            self.delayed_stop.map(|(at, _)| at),
+            self.table.next_restart(),
+            self.table.next_kill(),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
+
+    /// Polls the signalfd, process pidfds, control socket and captured output
+    /// descriptors; returns which are readable, or `None` when interrupted.
+    fn poll_sources(&self, signal_fd: &SignalFd) -> Result<Option<Ready>> {
+        let poll_timeout = match self.next_wakeup() {
+            Some(wakeup) => {
+                let remaining = wakeup.saturating_duration_since(Instant::now());
                 PollTimeout::try_from(remaining).unwrap_or(PollTimeout::MAX)
             }
-            _ => PollTimeout::NONE,
+            None => PollTimeout::NONE,
         };
+        let mut fds = vec![PollFd::new(signal_fd.as_fd(), PollFlags::POLLIN)];
+        fds.extend(
+            self.table
+                .exit_fds()
+                .into_iter()
+                .map(|fd| PollFd::new(fd, PollFlags::POLLIN)),
+        );
+        let exits_end = fds.len();
+        if let Some(control) = &self.control {
+            fds.extend(
+                control
+                    .poll_fds()
+                    .into_iter()
+                    .map(|fd| PollFd::new(fd, PollFlags::POLLIN)),
+            );
+        }
+        let control_end = fds.len();
+        if let Some(output) = &self.output {
+            fds.extend(
+                output
+                    .poll_fds()
+                    .into_iter()
+                    .map(|fd| PollFd::new(fd, PollFlags::POLLIN)),
+            );
+        }
         match poll(&mut fds, poll_timeout) {
             Ok(_) => {}
-            Err(err) => {
-                if err == Errno::EINTR {
-                    continue;
-                }
-                return Err(err).context("poll");
-            }
+            Err(Errno::EINTR) => return Ok(None),
+            Err(err) => return Err(err).context("poll"),
         }
-        let ready = fds[0]
-            .revents()
-            .unwrap_or_else(PollFlags::empty)
-            .contains(PollFlags::POLLIN);
-        if ready {
-            while let Some(info) = signal_fd.read_signal()? {
-                let sig = match Signal::try_from(info.ssi_signo as i32) {
-                    Ok(sig) => sig,
-                    Err(_) => {
-                        warn!("received unexpected signal {}", info.ssi_signo);
+        let readable = PollFlags::POLLIN | PollFlags::POLLHUP | PollFlags::POLLERR;
+        let mut flags: Vec<bool> = fds
+            .iter()
+            .map(|fd| {
+                fd.revents()
+                    .unwrap_or_else(PollFlags::empty)
+                    .intersects(readable)
+            })
+            .collect();
+        let output = flags.split_off(control_end);
+        let control = flags.split_off(exits_end);
+        Ok(Some(Ready {
+            signals: flags[0],
+            exited: flags[1..].contains(&true),
+            control,
+            output,
+        }))
+    }
+
+    fn run(mut self, signal_fd: &SignalFd) -> Result<i32> {
+        loop {
+            let Some(ready) = self.poll_sources(signal_fd)? else {
+                continue;
+            };
+            // Relay output first so lines written before an exit precede its log.
+            if let Some(output) = self.output.as_mut() {
+                output.service(&ready.output);
+            }
+            // SIGCHLD may lag behind (or coalesce with) a pidfd's exit report.
+            if ready.exited {
+                self.handle_sigchld()?;
+            }
+            if ready.signals {
+                while let Some(info) = signal_fd.read_signal()? {
+                    let sig = Sig::from_raw(info.ssi_signo as i32);
+                    if sig == Sig::HUP
+                        && let Some(output) = self.output.as_mut()
+                    {
+                        output.rotate_files();
+                        if self.consume_hup {
+                            debug!(signal = %sig, "rotated output files");
+                            continue;
+                        }
+                    }
+                    if sig == Sig::CHLD {
+                        self.handle_sigchld()?;
                         continue;
                     }
-                };
-                if sig == SIGCHLD {
-                    handle_sigchld(cli, child_pid, &mut main_exit)?;
-                } else {
-                    send_signal(use_pgroup, child_pid, sig);
-                    if cli.pgroup_kill
-                        && is_termination_signal(sig)
-                        && main_exit.is_none()
-                        && !sigkill_sent
-                    {
-                        let now = Instant::now();
-                        shutdown_deadline = Some(match shutdown_deadline {
-                            None => now + Duration::from_millis(cli.grace_ms),
-                            Some(_) => now,
-                        });
+                    match self.rewrites.apply(sig) {
+                        // Only interrupts that survive `--rewrite` unchanged count.
+                        Some(Sig::INT) if sig == Sig::INT && self.count_interrupt() => {
+                            self.force_kill();
+                        }
+                        Some(out) => {
+                            if out != sig {
+                                debug!(signal = %sig, to = %out, "rewriting signal");
+                            }
+                            self.forward_signal(out);
+                        }
+                        None => debug!(signal = %sig, "dropping signal per --rewrite"),
                     }
                 }
             }
+            if let Some((at, sig)) = self.delayed_stop
+                && Instant::now() >= at
+            {
+                self.delayed_stop = None;
+                self.forward_signal(sig);
+            }
+            if let Some(deadline) = self.shutdown_deadline
+                && self.exit_status.is_none()
+                && Instant::now() >= deadline
+            {
+                self.advance_stop();
+            }
+            self.handle_control(&ready.control);
+            self.table.kill_overdue(Instant::now());
+            self.start_due_restarts()?;
+            if self.exit_status.is_some() {
+                break;
+            }
         }
-        if let Some(deadline) = shutdown_deadline
-            && !sigkill_sent
-            && main_exit.is_none()
-            && Instant::now() >= deadline
+
+        let final_exit = compute_exit_code(self.exit_status, self.exit_map);
+        self.drain_output();
+        self.shutdown_remaining(signal_fd)?;
+        self.drain_output();
+        // Clean up before re-raising: a signal that kills tino skips everything after it.
+        self.remove_readiness_file();
+        drop(self.control.take());
+
+        if self.cli.reraise
+            && let Some(
+                exit @ Termination::Signaled {
+                    signal,
+                    core_dumped,
+                },
+            ) = self.exit_status
+            && exit.code() == final_exit
         {
-            info!("grace period expired; sending SIGKILL");
-            send_signal(use_pgroup, child_pid, SIGKILL);
-            sigkill_sent = true;
+            if std::process::id() == 1 {
+                warn!(
+                    signal = %signal,
+                    exit_code = final_exit,
+                    "cannot re-raise the signal as PID 1"
+                );
+            } else {
+                info!(signal = %signal, phase = "exit", "re-raising signal");
+                reraise(signal, core_dumped);
+                warn!(signal = %signal, "re-raised signal did not terminate tino");
+            }
         }
-        if main_exit.is_some() {
-            break;
+        info!(exit_code = final_exit, phase = "exit", "exiting");
+        Ok(final_exit)
+    }
+
+    /// Spawns process `idx`. A command that cannot be executed counts as
+    /// exiting with 126 or 127 and yields `None`.
+    fn spawn(&mut self, idx: usize) -> Result<Option<Pid>> {
+        let pipes = self
+            .output
+            .as_ref()
+            .map(|_| ChildPipes::new())
+            .transpose()?;
+        let process = self.table.get_mut(idx);
+        let pid = match process.spawn(
+            self.block,
+            self.cli.pgroup_kill,
+            pipes.as_ref().map(ChildPipes::child_fds),
+            &self.privileges,
+        ) {
+            Ok(pid) => pid,
+            Err(err) => {
+                let Some(failure) = err.downcast_ref::<ExecFailure>() else {
+                    return Err(err);
+                };
+                error!(
+                    process = %process.name,
+                    program = %failure.program,
+                    stage = failure.stage.as_str(),
+                    reason = %failure.reason(),
+                    errno = failure.errno as i32,
+                    exit_code = failure.exit_code(),
+                    "cannot execute command"
+                );
+                self.on_process_exit(idx);
+                return Ok(None);
+            }
+        };
+        if self.privileges.restricts() {
+            log_capabilities(&process.name, pid);
         }
+        if let (Some(output), Some(pipes)) = (self.output.as_mut(), pipes) {
+            output.attach(&process.name, pid, pipes);
+        }
+        Ok(Some(pid))
     }
 
-    let final_exit = compute_exit_code(main_exit, expect_zero);
+    fn drain_output(&mut self) {
+        if let Some(output) = self.output.as_mut() {
+            output.drain();
+        }
+    }
 
-    if use_pgroup {
-        info!("sending SIGTERM to PGID");
-        send_signal(true, child_pid, SIGTERM);
-        if !wait_for_children(cli.grace_ms, cli.warn_on_reap)? {
-            info!("still alive after {} ms; sending SIGKILL", cli.grace_ms);
-            send_signal(true, child_pid, SIGKILL);
-            let fully_reaped = wait_for_children(cli.grace_ms, cli.warn_on_reap)?;
-            if !fully_reaped {
-                warn!(
-                    "child processes still alive after SIGKILL wait of {} ms",
-                    cli.grace_ms
+    fn forward_signal(&mut self, sig: Sig) {
+        let terminating = is_termination_signal(sig);
+        if terminating && self.delay_stop(sig) {
+            return;
+        }
+        if terminating && !self.cli.stop_sequence.is_empty() {
+            // The ladder replaces the signal; repeating it skips the current wait.
+            debug!(signal = %sig, "advancing stop sequence");
+            self.enter_stopping();
+            if self.exit_status.is_none() {
+                self.advance_stop();
+            }
+            return;
+        }
+        if terminating && self.has_stop_action() {
+            self.enter_stopping();
+            if self.next_stage == 0 && self.exit_status.is_none() {
+                self.run_stop_action(sig);
+            }
+            self.arm_escalation();
+            return;
+        }
+        debug!(signal = %sig, "forwarding signal");
+        self.table.signal_all(sig);
+        if terminating {
+            self.enter_stopping();
+        }
+        // Plain forwarding escalates only when -g or --kill-scope asks for it.
+        if terminating && (self.cli.pgroup_kill || self.cli.kill_scope.is_some()) {
+            self.arm_escalation();
+        }
+    }
+
+    /// Records a received SIGINT and returns whether `--interrupt-kill-count`
+    /// of them have now arrived within `--interrupt-kill-window`.
+    fn count_interrupt(&mut self) -> bool {
+        let count = self.cli.interrupt_kill_count as usize;
+        if count == 0 {
+            return false;
+        }
+        let now = Instant::now();
+        let window = self.cli.interrupt_kill_window;
+        self.interrupts.retain(|&at| now - at < window);
+        self.interrupts.push(now);
+        self.interrupts.len() >= count
+    }
+
+    /// Skips whatever remains of the shutdown and sends SIGKILL right away.
+    fn force_kill(&mut self) {
+        warn!(
+            interrupts = self.interrupts.len(),
+            signal = %Sig::KILL,
+            phase = "shutdown",
+            "repeated interrupts; sending SIGKILL"
+        );
+        self.interrupts.clear();
+        self.enter_stopping();
+        self.delayed_stop = None;
+        self.shutdown_deadline = None;
+        self.next_stage = self.stop_stages.len();
+        self.table
+            .signal_all_scoped(Sig::KILL, self.cli.resolved_kill_scope());
+    }
+
+    /// Holds back the first termination signal for `--stop-delay` and returns
+    /// whether it did; a second one cancels the delay and is handled at once.
+    fn delay_stop(&mut self, sig: Sig) -> bool {
+        if self.delayed_stop.take().is_some() {
+            info!(signal = %sig, phase = "shutdown", "skipping the rest of the stop delay");
+            return false;
+        }
+        let Some(delay) = self.cli.stop_delay.filter(|_| !self.stop_delay_used) else {
+            return false;
+        };
+        self.stop_delay_used = true;
+        self.enter_stopping();
+        if self.exit_status.is_some() {
+            return false;
+        }
+        info!(
+            signal = %sig,
+            delay_ms = delay.as_millis() as u64,
+            phase = "shutdown",
+            "delaying stop"
+        );
+        self.delayed_stop = Some((Instant::now() + delay, sig));
+        true
+    }
+
+    fn has_stop_action(&self) -> bool {
+        self.stop_signal.is_some() || self.stop_command.is_some()
+    }
+
+    /// Sends `--stop-signal` or runs `--stop-command` in place of `received`.
+    fn run_stop_action(&mut self, received: Sig) {
+        if let Some(sig) = self.stop_signal {
+            info!(signal = %sig, received = %received, phase = "shutdown", "sending stop signal");
+            self.table.signal_all(sig);
+            return;
+        }
+        let Some(command) = self.stop_command.as_mut() else {
+            return;
+        };
+        match command.spawn(self.block, false, None, &self.privileges) {
+            Ok(pid) => info!(
+                pid = pid.as_raw(),
+                received = %received,
+                phase = "shutdown",
+                "running stop command"
+            ),
+            Err(err) => {
+                warn!(error = %format!("{err:#}"), "stop command failed; forwarding {received}");
+                self.table.signal_all(received);
+            }
+        }
+    }
+
+    /// Treats the first stop stage as already sent and schedules the next one
+    /// after its timeout; called again, it escalates immediately.
+    fn arm_escalation(&mut self) {
+        if self.exit_status.is_some() {
+            return;
+        }
+        if self.next_stage == 0 {
+            self.next_stage = 1;
+            self.shutdown_deadline = Some(Instant::now() + self.stop_stages[0].timeout);
+        } else if self.shutdown_deadline.is_some() {
+            self.shutdown_deadline = Some(Instant::now());
+        }
+    }
+
+    /// Sends the next stop stage and schedules the one after it.
+    fn advance_stop(&mut self) {
+        self.shutdown_deadline = None;
+        let Some(stage) = self.stop_stages.get(self.next_stage).copied() else {
+            return;
+        };
+        self.next_stage += 1;
+        info!(
+            signal = %stage.signal,
+            stage = self.next_stage,
+            phase = "shutdown",
+            "sending stop signal"
+        );
+        self.table
+            .signal_all_scoped(stage.signal, self.cli.resolved_kill_scope());
+        if self.next_stage < self.stop_stages.len() {
+            self.shutdown_deadline = Some(Instant::now() + stage.timeout);
+        }
+    }
+
+    fn handle_sigchld(&mut self) -> Result<()> {
+        let reaped = reap_children(&mut self.table, self.cli.warn_on_reap)?;
+        if let Some(command) = self.stop_command.as_mut()
+            && let Some(&(pid, termination)) = reaped
+                .secondary
+                .iter()
+                .find(|(pid, _)| command.running && command.pid == Some(*pid))
+        {
+            command.mark_exited(termination);
+            info!(
+                pid = pid.as_raw(),
+                exit_code = termination.code(),
+                phase = "shutdown",
+                "stop command exited"
+            );
+        }
+        for idx in reaped.exited {
+            self.on_process_exit(idx);
+        }
+        Ok(())
+    }
+
+    fn on_process_exit(&mut self, idx: usize) {
+        let process = self.table.get(idx);
+        let termination = process.last_exit.unwrap_or(Termination::Exited(0));
+        let pid = process.pid.map_or(0, Pid::as_raw);
+        let signal = termination.signal().map(|sig| sig.to_string());
+        if idx == 0 {
+            debug!(
+                process = %process.name,
+                pid,
+                exit_code = termination.code(),
+                signal = signal.as_deref(),
+                "process exited"
+            );
+        } else {
+            info!(
+                process = %process.name,
+                pid,
+                exit_code = termination.code(),
+                signal = signal.as_deref(),
+                "process exited"
+            );
+        }
+        if self.exit_status.is_some() {
+            return;
+        }
+        let stopping = self.stopping;
+        let process = self.table.get_mut(idx);
+        if std::mem::take(&mut process.restart_requested) && !stopping {
+            process.restart_at = Some(Instant::now());
+            process.restart_count += 1;
+            info!(
+                process = %process.name,
+                restart = process.restart_count,
+                "restarting process on request"
+            );
+            return;
+        }
+        if self.should_restart(termination) {
+            let cli = self.cli;
+            let process = self.table.get_mut(idx);
+            match process.schedule_restart(
+                Duration::from_millis(cli.restart_delay_ms),
+                Duration::from_millis(cli.restart_max_delay_ms),
+                Duration::from_millis(cli.restart_reset_ms),
+                cli.max_restarts,
+            ) {
+                Some(delay) => {
+                    info!(
+                        process = %process.name,
+                        delay_ms = delay.as_millis() as u64,
+                        restart = process.restart_count,
+                        "scheduling restart"
+                    );
+                    return;
+                }
+                None => warn!(
+                    process = %process.name,
+                    restarts = process.consecutive_restarts,
+                    "consecutive restart limit reached; giving up"
+                ),
+            }
+        }
+        self.settle_exit(idx);
+    }
+
+    /// Applies the exit policy to a process that exited for good.
+    fn settle_exit(&mut self, idx: usize) {
+        let exited = self.table.get(idx).last_exit;
+        self.exit_status = match self.cli.exit_policy {
+            ExitPolicy::Main if idx == 0 => exited,
+            ExitPolicy::Any => exited,
+            ExitPolicy::All if !self.table.any_active() => self.table.main().last_exit,
+            _ => None,
+        };
+    }
+
+    fn should_restart(&self, termination: Termination) -> bool {
+        if self.stopping {
+            return false;
+        }
+        match self.cli.restart {
+            RestartPolicy::No => false,
+            RestartPolicy::OnFailure => compute_exit_code(Some(termination), self.exit_map) != 0,
+            RestartPolicy::Always => true,
+        }
+    }
+
+    fn start_due_restarts(&mut self) -> Result<()> {
+        if self.exit_status.is_some() {
+            return Ok(());
+        }
+        for idx in self.table.pending_restarts(Some(Instant::now())) {
+            if let Some(pid) = self.spawn(idx)? {
+                let process = self.table.get(idx);
+                info!(
+                    process = %process.name,
+                    pid = pid.as_raw(),
+                    restart = process.restart_count,
+                    "restarted process"
                 );
             }
         }
-    } else {
-        let _ = wait_for_children(cli.grace_ms, cli.warn_on_reap)?;
+        Ok(())
     }
 
-    info!("exiting with {}", final_exit);
-    Ok(final_exit)
-}
+    fn enter_stopping(&mut self) {
+        if !self.stopping {
+            self.stopping = true;
+            self.remove_readiness_file();
+            self.cancel_restarts();
+        }
+    }
 
-fn is_termination_signal(sig: Signal) -> bool {
-    sig == SIGTERM || sig == SIGINT || sig == SIGQUIT
-}
+    /// Withdraws `--readiness-file` so probes fail while tino shuts down.
+    fn remove_readiness_file(&self) {
+        let Some(path) = self.cli.readiness_file.as_deref() else {
+            return;
+        };
+        match std::fs::remove_file(path) {
+            Ok(()) => info!(path = %path.display(), "removed readiness file"),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => warn!(path = %path.display(), error = %err, "cannot remove readiness file"),
+        }
+    }
 
-fn handle_sigchld(cli: &Cli, child_pid: Pid, main_exit: &mut Option<i32>) -> Result<()> {
-    loop {
-        match waitpid(Pid::from_raw(-1), Some(WaitPidFlag::WNOHANG)) {
-            Ok(WaitStatus::Exited(pid, code)) => {
-                if pid == child_pid {
-                    *main_exit = Some(code);
-                } else if cli.warn_on_reap {
-                    warn!("reaped secondary PID {}", pid);
-                } else {
-                    debug!("reaped secondary PID {}", pid);
+    fn handle_control(&mut self, ready: &[bool]) {
+        let pending = match self.control.as_mut() {
+            Some(control) => control.service(ready),
+            None => return,
+        };
+        for request in pending {
+            let response = match &request.request {
+                Ok(parsed) => {
+                    debug!(request = %parsed, phase = "control", "control request");
+                    self.execute(parsed)
+                }
+                Err(err) => Response::error(err.as_str()),
+            };
+            request.reply(&response);
+        }
+    }
+
+    fn execute(&mut self, request: &Request) -> Response {
+        match request {
+            Request::Status => Response {
+                status: Some(self.status()),
+                ..Response::ok()
+            },
+            Request::Signal { signal, target } => {
+                let Some(sig) = signal_by_name(signal) else {
+                    return Response::error(format!("unsupported signal {signal}"));
+                };
+                match target.as_deref() {
+                    None => self.forward_signal(sig),
+                    Some(name) => match self.table.position(name) {
+                        Some(idx) => self.table.get(idx).signal(sig),
+                        None => return Response::error(format!("unknown process '{name}'")),
+                    },
                 }
+                Response::ok()
             }
-            Ok(WaitStatus::Signaled(pid, sig, _)) => {
-                let code = 128 + sig as i32;
-                if pid == child_pid {
-                    *main_exit = Some(code);
-                } else if cli.warn_on_reap {
-                    warn!("reaped secondary PID {}", pid);
-                } else {
-                    debug!("reaped secondary PID {}", pid);
+            Request::Stop { grace_ms } => {
+                info!(phase = "control", "stop requested via control socket");
+                self.enter_stopping();
+                self.delayed_stop = None;
+                // An explicit grace runs the plain SIGTERM/SIGKILL ladder from the start.
+                if let Some(ms) = grace_ms {
+                    self.stop_stages = default_stop_stages(Duration::from_millis(*ms));
+                    self.next_stage = 0;
                 }
+                if self.next_stage == 0 {
+                    if self.has_stop_action() {
+                        self.run_stop_action(Sig::TERM);
+                        self.arm_escalation();
+                    } else {
+                        self.advance_stop();
+                    }
+                }
+                Response::ok()
             }
-            Ok(WaitStatus::Stopped(pid, sig)) => {
-                if cli.warn_on_reap {
-                    warn!("child PID {} stopped by signal {:?}", pid, sig);
-                } else {
-                    debug!("child PID {} stopped by signal {:?}", pid, sig);
+            Request::Restart { target } => self.request_restart(target.as_deref()),
+        }
+    }
+
+    fn request_restart(&mut self, target: Option<&str>) -> Response {
+        if self.stopping || self.exit_status.is_some() {
+            return Response::error("tino is shutting down");
+        }
+        let indices: Vec<usize> = match target {
+            None => (0..self.table.len()).collect(),
+            Some(name) => match self.table.position(name) {
+                Some(idx) => vec![idx],
+                None => return Response::error(format!("unknown process '{name}'")),
+            },
+        };
+        let now = Instant::now();
+        let grace = Duration::from_millis(self.cli.grace_ms);
+        for idx in indices {
+            let process = self.table.get_mut(idx);
+            if process.running {
+                info!(
+                    process = %process.name,
+                    phase = "control",
+                    "restart requested via control socket"
+                );
+                process.restart_requested = true;
+                process.kill_at = Some(now + grace);
+                process.signal(Sig::TERM);
+            } else {
+                if process.restart_at.is_none() {
+                    process.restart_count += 1;
                 }
-                break;
+                process.restart_at = Some(now);
             }
-            Ok(WaitStatus::StillAlive) | Ok(WaitStatus::Continued(_)) => break,
-            Err(Errno::ECHILD) => break,
-            Err(Errno::EINTR) => continue,
-            Ok(status) => {
-                debug!("waitpid yielded unhandled state: {:?}", status);
-                break;
+        }
+        Response::ok()
+    }
+
+    fn status(&self) -> Status {
+        Status {
+            pid: std::process::id(),
+            uptime_ms: self.started.elapsed().as_millis() as u64,
+            stopping: self.stopping,
+            processes: self
+                .table
+                .iter()
+                .map(|p| ProcessStatus {
+                    name: p.name.clone(),
+                    pid: p.pid.map(Pid::as_raw),
+                    running: p.running,
+                    uptime_ms: p
+                        .started_at
+                        .filter(|_| p.running)
+                        .map(|started| started.elapsed().as_millis() as u64),
+                    restarts: p.restart_count,
+                    last_exit: p.last_exit.map(|t| ExitStatus {
+                        code: t.code(),
+                        signal: t.signal().map(|sig| sig.to_string()),
+                    }),
+                })
+                .collect(),
+        }
+    }
+
+    /// Drops pending restarts once shutdown begins so their last exit counts.
+    fn cancel_restarts(&mut self) {
+        for idx in self.table.pending_restarts(None) {
+            self.table.get_mut(idx).restart_at = None;
+            if self.exit_status.is_none() {
+                self.settle_exit(idx);
             }
-            Err(e) => bail!("waitpid: {e}"),
         }
     }
-    Ok(())
-}
 
-fn compute_exit_code(main_exit: Option<i32>, expect_zero: &HashSet<u8>) -> i32 {
-    let code = main_exit.unwrap_or(0);
-    if expect_zero.contains(&(code as u8)) {
-        0
-    } else {
-        code
+    /// Terminates sidecars that outlived the exit decision and sweeps managed
+    /// process groups, walking `--straggler-sequence` until nothing is left.
+    fn shutdown_remaining(&mut self, signal_fd: &SignalFd) -> Result<()> {
+        let warn_on_reap = self.cli.warn_on_reap;
+        let stages = self.straggler_stages.clone();
+        let scope = self.cli.resolved_kill_scope();
+        let targets = if scope == KillScope::All {
+            reap_children(&mut self.table, warn_on_reap)?;
+            descendants(Pid::this()).len()
+        } else {
+            self.table
+                .iter()
+                .filter(|p| p.running || p.use_pgroup)
+                .count()
+        };
+        if targets == 0 {
+            let _ = wait_for_children(
+                &mut self.table,
+                self.output.as_mut(),
+                signal_fd,
+                stages[0].timeout,
+                warn_on_reap,
+            )?;
+            return Ok(());
+        }
+
+        let mut previous: Option<Stage> = None;
+        for stage in &stages {
+            match previous {
+                None if scope == KillScope::All => {}
+                None => self.log_first_straggler_stage(stage.signal),
+                Some(previous) => info!(
+                    timeout_ms = previous.timeout.as_millis() as u64,
+                    signal = %stage.signal,
+                    phase = "shutdown",
+                    "children still alive after {}; sending {}",
+                    previous.signal,
+                    stage.signal
+                ),
+            }
+            if scope == KillScope::All {
+                signal_stragglers(&descendants(Pid::this()), stage.signal);
+            } else {
+                self.table.signal_all_scoped(stage.signal, scope);
+            }
+            if wait_for_children(
+                &mut self.table,
+                self.output.as_mut(),
+                signal_fd,
+                stage.timeout,
+                warn_on_reap,
+            )? {
+                return Ok(());
+            }
+            previous = Some(*stage);
+        }
+        warn!(
+            phase = "shutdown",
+            "child processes still alive after the last straggler stage"
+        );
+        Ok(())
+    }
+
+    fn log_first_straggler_stage(&self, signal: Sig) {
+        for process in self.table.iter().filter(|p| p.running || p.use_pgroup) {
+            let pid = process.pid.map_or(0, Pid::as_raw);
+            if process.use_pgroup {
+                info!(
+                    process = %process.name,
+                    pgid = pid,
+                    signal = %signal,
+                    phase = "shutdown",
+                    "sending {signal} to process group"
+                );
+            } else {
+                info!(
+                    process = %process.name,
+                    pid,
+                    signal = %signal,
+                    phase = "shutdown",
+                    "sending {signal}"
+                );
+            }
+        }
     }
 }
 
-fn wait_for_children(timeout_ms: u64, warn_on_reap: bool) -> Result<bool> {
-    let start = Instant::now();
-    let timeout = Duration::from_millis(timeout_ms);
+fn is_termination_signal(sig: Sig) -> bool {
+    sig == Sig::TERM || sig == Sig::INT || sig == Sig::QUIT
+}
+
+#[derive(Default)]
+struct Reaped {
+    /// Table indices of supervised processes that exited.
+    exited: Vec<usize>,
+    /// Reaped children outside the process table.
+    secondary: Vec<(Pid, Termination)>,
+    /// `waitpid` reported that no children remain.
+    no_children: bool,
+}
+
+fn reap_children(table: &mut ProcessTable, warn_on_reap: bool) -> Result<Reaped> {
+    let mut reaped = Reaped::default();
     loop {
-        match waitpid(Pid::from_raw(-1), Some(WaitPidFlag::WNOHANG)) {
-            Ok(WaitStatus::StillAlive) => (),
-            Ok(WaitStatus::Exited(pid, _)) | Ok(WaitStatus::Signaled(pid, _, _)) => {
-                if warn_on_reap {
-                    warn!("reaped secondary PID {}", pid);
-                } else {
-                    debug!("reaped secondary PID {}", pid);
+        // Raw `waitpid`: nix cannot decode statuses for real-time signals.
+        let mut status: libc::c_int = 0;
+        // SAFETY: `status` is a valid out-pointer for the duration of the call.
+        let raw_pid = unsafe { libc::waitpid(-1, &mut status, libc::WNOHANG) };
+        if raw_pid == 0 {
+            break;
+        }
+        if raw_pid == -1 {
+            match Errno::last() {
+                Errno::ECHILD => {
+                    reaped.no_children = true;
+                    break;
                 }
-                continue;
+                Errno::EINTR => continue,
+                e => bail!("waitpid: {e}"),
             }
-            Ok(_) => continue,
-            Err(Errno::ECHILD) => return Ok(true),
-            Err(Errno::EINTR) => continue,
-            Err(e) => bail!("waitpid: {e}"),
         }
-        if timeout_ms == 0 {
-            return Ok(false);
+        let pid = Pid::from_raw(raw_pid);
+        let termination = if libc::WIFEXITED(status) {
+            Termination::Exited(libc::WEXITSTATUS(status))
+        } else if libc::WIFSIGNALED(status) {
+            Termination::Signaled {
+                signal: Sig::from_raw(libc::WTERMSIG(status)),
+                core_dumped: libc::WCOREDUMP(status),
+            }
+        } else {
+            debug!(
+                pid = pid.as_raw(),
+                status, "waitpid yielded unhandled state"
+            );
+            break;
+        };
+        if let Some(idx) = table.record_exit(pid, termination) {
+            reaped.exited.push(idx);
+            continue;
+        }
+        reaped.secondary.push((pid, termination));
+        if warn_on_reap {
+            warn!(
+                pid = pid.as_raw(),
+                exit_code = termination.code(),
+                "reaped secondary PID"
+            );
+        } else {
+            debug!(
+                pid = pid.as_raw(),
+                exit_code = termination.code(),
+                "reaped secondary PID"
+            );
         }
-        let elapsed = start.elapsed();
-        if elapsed >= timeout {
+    }
+    Ok(reaped)
+}
+
+fn compute_exit_code(exit: Option<Termination>, exit_map: &ExitCodeMap) -> i32 {
+    let termination = exit.unwrap_or(Termination::Exited(0));
+    let signal = termination.signal().map(|sig| sig.to_string());
+    exit_map.apply(termination.code(), signal.as_deref())
+}
+
+/// Reaps until no children remain or `timeout` passes, sleeping in `poll` on
+/// SIGCHLD and the supervised processes' pidfds in between. Captured output
+/// keeps being relayed so a child never blocks on a full pipe while it stops.
+/// Returns whether every child is gone.
+fn wait_for_children(
+    table: &mut ProcessTable,
+    mut output: Option<&mut OutputRelay>,
+    signal_fd: &SignalFd,
+    timeout: Duration,
+    warn_on_reap: bool,
+) -> Result<bool> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if reap_children(table, warn_on_reap)?.no_children {
+            return Ok(true);
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
             return Ok(false);
         }
-        let remaining = timeout - elapsed;
-        thread::sleep(remaining.min(Duration::from_millis(10)));
+        let mut fds = vec![PollFd::new(signal_fd.as_fd(), PollFlags::POLLIN)];
+        fds.extend(
+            table
+                .exit_fds()
+                .into_iter()
+                .map(|fd| PollFd::new(fd, PollFlags::POLLIN)),
+        );
+        let output_start = fds.len();
+        if let Some(output) = output.as_deref() {
+            fds.extend(
+                output
+                    .poll_fds()
+                    .into_iter()
+                    .map(|fd| PollFd::new(fd, PollFlags::POLLIN)),
+            );
+        }
+        // Round up so a sub-millisecond remainder does not spin.
+        let wait = PollTimeout::try_from(remaining + Duration::from_micros(999))
+            .unwrap_or(PollTimeout::MAX);
+        match poll(&mut fds, wait) {
+            Ok(_) | Err(Errno::EINTR) => {}
+            Err(err) => return Err(err).context("poll"),
+        }
+        let readable = PollFlags::POLLIN | PollFlags::POLLHUP | PollFlags::POLLERR;
+        let ready: Vec<bool> = fds[output_start..]
+            .iter()
+            .map(|fd| {
+                fd.revents()
+                    .unwrap_or_else(PollFlags::empty)
+                    .intersects(readable)
+            })
+            .collect();
+        drop(fds);
+        if let Some(output) = output.as_deref_mut() {
+            output.service(&ready);
+        }
+        // Drain the signalfd so it stops polling readable; the exit decision
+        // is made, so late signals no longer change anything.
+        while let Some(info) = signal_fd.read_signal()? {
+            let sig = Sig::from_raw(info.ssi_signo as i32);
+            if sig != Sig::CHLD {
+                debug!(signal = %sig, phase = "shutdown", "ignoring signal during final sweep");
+            }
+        }
     }
 }
 
@@ -227,6 +1106,7 @@ fn wait_for_children(timeout_ms: u64, warn_on_reap: bool) -> Result<bool> {
 mod tests {
     use super::*;
     use crate::platform;
+    use nix::sys::signalfd::SfdFlags;
 
     #[test]
     fn license_text_includes_mit_header() {
@@ -235,13 +1115,36 @@ mod tests {
 
     #[test]
     fn signal_lookup_accepts_variants_with_or_without_prefix() {
+        assert_eq!(super::signals::signal_by_name("TERM"), Some(Sig::TERM));
+        assert_eq!(super::signals::signal_by_name("SIGTERM"), Some(Sig::TERM));
+    }
+
+    #[test]
+    fn rewrites_translate_or_drop_signals() {
+        let rewrites = SignalRewrites::resolve(&[
+            crate::cli::parse_rewrite("TERM:QUIT").unwrap(),
+            crate::cli::parse_rewrite("WINCH:0").unwrap(),
+        ])
+        .unwrap();
+        assert_eq!(rewrites.apply(Sig::TERM), Some(Sig::QUIT));
+        assert_eq!(rewrites.apply(Sig::from_raw(libc::SIGWINCH)), None);
         assert_eq!(
-            super::signals::signal_by_name("TERM"),
-            Some(Signal::SIGTERM)
+            rewrites.apply(Sig::from_raw(libc::SIGHUP)),
+            Some(Sig::from_raw(libc::SIGHUP))
         );
+    }
+
+    #[test]
+    fn forwarded_set_applies_additions_and_removals() {
+        let forwarded =
+            forwarded_signals(&["SIGALRM".into(), "SIGTERM".into()], &["SIGWINCH".into()]).unwrap();
+        assert!(forwarded.contains(&Sig::from_raw(libc::SIGALRM)));
+        assert!(forwarded.contains(&Sig::TERM));
+        assert!(!forwarded.contains(&Sig::from_raw(libc::SIGWINCH)));
         assert_eq!(
-            super::signals::signal_by_name("SIGTERM"),
-            Some(Signal::SIGTERM)
+            forwarded.iter().filter(|&&s| s == Sig::TERM).count(),
+            1,
+            "default signals must not be duplicated"
         );
     }
 
@@ -252,21 +1155,64 @@ mod tests {
 
     #[test]
     fn init_logging_is_idempotent() {
-        platform::init_logging(0);
-        platform::init_logging(1);
+        platform::init_logging(0, platform::LogOptions::default());
+        platform::init_logging(1, platform::LogOptions::default());
     }
 
     #[test]
     fn wait_for_children_without_children_succeeds() {
-        assert!(wait_for_children(0, false).unwrap());
+        let _lock = child_test_lock();
+        let main = Process::new(MAIN_PROCESS_NAME, &["/bin/true".to_string()]).unwrap();
+        let mut table = ProcessTable::new(main);
+        assert!(
+            wait_for_children(&mut table, None, &empty_signal_fd(), Duration::ZERO, false).unwrap()
+        );
+    }
+
+    fn empty_signal_fd() -> SignalFd {
+        SignalFd::with_flags(&SigSet::empty(), SfdFlags::SFD_NONBLOCK).unwrap()
+    }
+
+    #[test]
+    fn wait_for_children_wakes_on_exit() {
+        let _lock = child_test_lock();
+        let main = Process::new(MAIN_PROCESS_NAME, &["sleep".to_string(), "0.1".to_string()]);
+        let mut table = ProcessTable::new(main.unwrap());
+        table
+            .get_mut(0)
+            .spawn(SigSet::empty(), false, None, &Privileges::default())
+            .unwrap();
+
+        let started = Instant::now();
+        let signal_fd = empty_signal_fd();
+        assert!(
+            wait_for_children(&mut table, None, &signal_fd, Duration::from_secs(10), false)
+                .unwrap()
+        );
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(table.main().last_exit, Some(Termination::Exited(0)));
     }
 
     #[test]
     fn compute_exit_code_remaps_expected_values() {
-        let mut expect_zero = HashSet::new();
-        expect_zero.insert(3);
-        assert_eq!(compute_exit_code(Some(3), &expect_zero), 0);
-        assert_eq!(compute_exit_code(Some(5), &expect_zero), 5);
-        assert_eq!(compute_exit_code(None, &expect_zero), 0);
+        let exit_map = ExitCodeMap::new(
+            &[3],
+            &[crate::exit_code::parse_exit_mapping("SIGTERM=7").unwrap()],
+        );
+        let exited = |code| Some(Termination::Exited(code));
+        assert_eq!(compute_exit_code(exited(3), &exit_map), 0);
+        assert_eq!(compute_exit_code(exited(5), &exit_map), 5);
+        assert_eq!(compute_exit_code(exited(143), &exit_map), 143);
+        assert_eq!(
+            compute_exit_code(
+                Some(Termination::Signaled {
+                    signal: Sig::TERM,
+                    core_dumped: false
+                }),
+                &exit_map
+            ),
+            7
+        );
+        assert_eq!(compute_exit_code(None, &exit_map), 0);
     }
 }
@@ -0,0 +1,330 @@
+use crate::cli::OutputMode;
+use anyhow::{Context, Result};
+use nix::{errno::Errno, unistd::Pid};
+use serde::Serialize;
+use std::{
+    io::{self, Write},
+    os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd},
+    path::Path,
+};
+use tracing::{debug, warn};
+
+use super::rotate::{RotatingFile, Rotation};
+
+/// Longest partial line kept before it is emitted without a newline.
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Pipes replacing a child's stdout and stderr for `--output` capture.
+pub(super) struct ChildPipes {
+    read: [OwnedFd; 2],
+    write: [OwnedFd; 2],
+}
+
+impl ChildPipes {
+    pub(super) fn new() -> Result<Self> {
+        let (out_read, out_write) = pipe().context("stdout pipe")?;
+        let (err_read, err_write) = pipe().context("stderr pipe")?;
+        Ok(Self {
+            read: [out_read, err_read],
+            write: [out_write, err_write],
+        })
+    }
+
+    /// Write ends the child installs as fds 1 and 2.
+    pub(super) fn child_fds(&self) -> [RawFd; 2] {
+        [self.write[0].as_raw_fd(), self.write[1].as_raw_fd()]
+    }
+}
+
+/// A blocking pipe whose ends are closed on exec, as `(read, write)`.
+pub(super) fn cloexec_pipe() -> io::Result<(OwnedFd, OwnedFd)> {
+    let mut fds = [0; 2];
+    // SAFETY: `fds` has room for the two descriptors `pipe2` returns.
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: both descriptors were just created and are owned by nobody else.
+    Ok(unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) })
+}
+
+/// A close-on-exec pipe with a non-blocking read end for the poll loop.
+fn pipe() -> io::Result<(OwnedFd, OwnedFd)> {
+    let (read, write) = cloexec_pipe()?;
+    // SAFETY: F_SETFL on a descriptor we own.
+    if unsafe { libc::fcntl(read.as_raw_fd(), libc::F_SETFL, libc::O_NONBLOCK) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((read, write))
+}
+
+struct Source {
+    process: String,
+    pid: i32,
+    stream: Stream,
+    fd: OwnedFd,
+    buf: Vec<u8>,
+}
+
+/// Reads captured child output from the poll loop and re-emits it line by line.
+pub(super) struct OutputRelay {
+    mode: OutputMode,
+    timestamps: bool,
+    sources: Vec<Source>,
+    files: Vec<RotatingFile>,
+    /// Index into `files` for stdout and stderr; `None` writes to tino's own stream.
+    targets: [Option<usize>; 2],
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time: Option<&'a str>,
+    process: &'a str,
+    pid: i32,
+    stream: Stream,
+    line: &'a str,
+}
+
+impl OutputRelay {
+    /// Returns `None` for `--output inherit`, where children keep tino's stdio.
+    pub(super) fn new(mode: OutputMode, timestamps: bool) -> Option<Self> {
+        (mode != OutputMode::Inherit).then_some(Self {
+            mode,
+            timestamps,
+            sources: Vec::new(),
+            files: Vec::new(),
+            targets: [None, None],
+        })
+    }
+
+    /// Sends captured stdout and/or stderr to files instead of tino's streams.
+    /// Both streams share one file when the paths are equal.
+    pub(super) fn open_files(
+        &mut self,
+        stdout: Option<&Path>,
+        stderr: Option<&Path>,
+        rotation: Rotation,
+    ) -> Result<()> {
+        for (slot, path) in [stdout, stderr].into_iter().enumerate() {
+            let Some(path) = path else { continue };
+            let idx = match self.files.iter().position(|f| f.path() == path) {
+                Some(idx) => idx,
+                None => {
+                    let file = RotatingFile::open(path, rotation)
+                        .with_context(|| format!("open output file {}", path.display()))?;
+                    self.files.push(file);
+                    self.files.len() - 1
+                }
+            };
+            self.targets[slot] = Some(idx);
+        }
+        Ok(())
+    }
+
+    /// Rotates every output file (on SIGHUP).
+    pub(super) fn rotate_files(&mut self) {
+        for file in &mut self.files {
+            if let Err(err) = file.rotate() {
+                warn!(path = %file.path().display(), error = %err, "rotating output file failed");
+            }
+        }
+    }
+
+    /// Starts relaying a freshly spawned child; the write ends are closed here.
+    pub(super) fn attach(&mut self, process: &str, pid: Pid, pipes: ChildPipes) {
+        let ChildPipes { read, write } = pipes;
+        drop(write);
+        for (fd, stream) in read.into_iter().zip([Stream::Stdout, Stream::Stderr]) {
+            self.sources.push(Source {
+                process: process.to_string(),
+                pid: pid.as_raw(),
+                stream,
+                fd,
+                buf: Vec::new(),
+            });
+        }
+    }
+
+    pub(super) fn poll_fds(&self) -> Vec<BorrowedFd<'_>> {
+        self.sources.iter().map(|s| s.fd.as_fd()).collect()
+    }
+
+    /// Reads sources flagged in `ready` (aligned with [`OutputRelay::poll_fds`]).
+    pub(super) fn service(&mut self, ready: &[bool]) {
+        let mut idx = self.sources.len();
+        while idx > 0 {
+            idx -= 1;
+            if ready.get(idx).copied().unwrap_or(false) {
+                self.read_source(idx);
+            }
+        }
+    }
+
+    /// Reads whatever is buffered in every pipe without waiting for more.
+    pub(super) fn drain(&mut self) {
+        let mut idx = self.sources.len();
+        while idx > 0 {
+            idx -= 1;
+            self.read_source(idx);
+        }
+    }
+
+    fn read_source(&mut self, idx: usize) {
+        let mut chunk = [0u8; 8192];
+        loop {
+            let source = &mut self.sources[idx];
+            // SAFETY: `chunk` is valid for writes of its full length.
+            let n = unsafe {
+                libc::read(
+                    source.fd.as_raw_fd(),
+                    chunk.as_mut_ptr() as *mut libc::c_void,
+                    chunk.len(),
+                )
+            };
+            match n {
+                0 => {
+                    let source = self.sources.swap_remove(idx);
+                    if !source.buf.is_empty() {
+                        self.emit(&source.process, source.pid, source.stream, &source.buf);
+                    }
+                    return;
+                }
+                n if n > 0 => {
+                    source.buf.extend_from_slice(&chunk[..n as usize]);
+                    self.flush_lines(idx);
+                }
+                _ => match Errno::last() {
+                    Errno::EINTR => continue,
+                    Errno::EAGAIN => return,
+                    err => {
+                        debug!(error = %err, "dropping output pipe");
+                        self.sources.swap_remove(idx);
+                        return;
+                    }
+                },
+            }
+        }
+    }
+
+    fn flush_lines(&mut self, idx: usize) {
+        let lines = take_lines(&mut self.sources[idx].buf);
+        let (process, pid, stream) = {
+            let source = &self.sources[idx];
+            (source.process.clone(), source.pid, source.stream)
+        };
+        for line in lines {
+            self.emit(&process, pid, stream, &line);
+        }
+    }
+
+    fn emit(&mut self, process: &str, pid: i32, stream: Stream, line: &[u8]) {
+        let time = self.timestamps.then(crate::platform::rfc3339_now);
+        let out = self.format_line(process, pid, stream, line, time.as_deref());
+        let slot = match stream {
+            Stream::Stdout => 0,
+            Stream::Stderr => 1,
+        };
+        let result = match (self.targets[slot], stream) {
+            (Some(idx), _) => self.files[idx].write(out.as_bytes()),
+            (None, Stream::Stdout) => io::stdout().lock().write_all(out.as_bytes()),
+            (None, Stream::Stderr) => io::stderr().lock().write_all(out.as_bytes()),
+        };
+        if let Err(err) = result {
+            debug!(error = %err, "relaying child output failed");
+        }
+    }
+
+    fn format_line(
+        &self,
+        process: &str,
+        pid: i32,
+        stream: Stream,
+        line: &[u8],
+        time: Option<&str>,
+    ) -> String {
+        let line = String::from_utf8_lossy(line);
+        let mut out = match self.mode {
+            OutputMode::Json => serde_json::to_string(&Record {
+                time,
+                process,
+                pid,
+                stream,
+                line: &line,
+            })
+            .unwrap_or_default(),
+            OutputMode::Prefixed | OutputMode::Plain | OutputMode::Inherit => {
+                let mut out = String::new();
+                if let Some(time) = time {
+                    out.push_str(time);
+                    out.push(' ');
+                }
+                if self.mode == OutputMode::Prefixed {
+                    out.push('[');
+                    out.push_str(process);
+                    out.push_str("] ");
+                }
+                out.push_str(&line);
+                out
+            }
+        };
+        out.push('\n');
+        out
+    }
+}
+
+/// Removes complete lines from `buf`, plus an overlong partial line.
+fn take_lines(buf: &mut Vec<u8>) -> Vec<Vec<u8>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    while let Some(pos) = buf[start..].iter().position(|&b| b == b'\n') {
+        lines.push(buf[start..start + pos].to_vec());
+        start += pos + 1;
+    }
+    if buf.len() - start > MAX_LINE_BYTES {
+        lines.push(buf[start..].to_vec());
+        start = buf.len();
+    }
+    buf.drain(..start);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_lines_stay_buffered() {
+        let mut buf = b"first\nsecond\nthi".to_vec();
+        assert_eq!(
+            take_lines(&mut buf),
+            vec![b"first".to_vec(), b"second".to_vec()]
+        );
+        assert_eq!(buf, b"thi");
+
+        let mut long = vec![b'x'; MAX_LINE_BYTES + 1];
+        assert_eq!(take_lines(&mut long).len(), 1);
+        assert!(long.is_empty());
+    }
+
+    #[test]
+    fn lines_are_prefixed_or_wrapped() {
+        let prefixed = OutputRelay::new(OutputMode::Prefixed, true).unwrap();
+        assert_eq!(
+            prefixed.format_line("web", 42, Stream::Stdout, b"hello", Some("T")),
+            "T [web] hello\n"
+        );
+        let json = OutputRelay::new(OutputMode::Json, false).unwrap();
+        assert_eq!(
+            json.format_line("web", 42, Stream::Stderr, b"oops", None),
+            "{\"process\":\"web\",\"pid\":42,\"stream\":\"stderr\",\"line\":\"oops\"}\n"
+        );
+        assert!(OutputRelay::new(OutputMode::Inherit, false).is_none());
+    }
+}
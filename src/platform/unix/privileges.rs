@@ -0,0 +1,230 @@
+use crate::capabilities::{ALL_CAPABILITIES, CAPABILITY_NAMES, capability_number};
+use crate::cli::Cli;
+use anyhow::Result;
+use nix::{errno::Errno, unistd::Pid};
+use std::fs;
+use tracing::debug;
+
+use super::child::ExecStage;
+use super::user::{Credentials, drop_privileges};
+
+/// One bit per capability number.
+type CapMask = u64;
+
+const LINUX_CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+
+#[repr(C)]
+struct CapHeader {
+    version: u32,
+    pid: libc::c_int,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct CapData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
+
+/// What a child changes about its privileges between fork and exec.
+#[derive(Debug, Default)]
+pub(super) struct Privileges {
+    /// `--user`.
+    pub user: Option<Credentials>,
+    /// `--no-new-privs`.
+    pub no_new_privs: bool,
+    /// `--drop-caps`, removed from the bounding set.
+    drop: CapMask,
+    /// `--keep-caps`, spared from `drop` and raised into the ambient set.
+    keep: CapMask,
+}
+
+impl Privileges {
+    pub(super) fn from_cli(cli: &Cli) -> Result<Self> {
+        let all = known_capabilities();
+        let mask = |names: &[String]| {
+            names
+                .iter()
+                .fold(0, |mask, name| match capability_number(name) {
+                    Some(cap) => mask | 1 << cap,
+                    None if name == ALL_CAPABILITIES => mask | all,
+                    None => mask,
+                })
+                & all
+        };
+        let keep = mask(&cli.keep_caps);
+        Ok(Self {
+            user: cli.user.as_deref().map(Credentials::resolve).transpose()?,
+            no_new_privs: cli.no_new_privs,
+            drop: mask(&cli.drop_caps) & !keep,
+            keep,
+        })
+    }
+
+    /// Whether spawned children are worth a capability debug log.
+    pub(super) fn restricts(&self) -> bool {
+        self.no_new_privs || self.drop != 0 || self.keep != 0
+    }
+
+    /// Applies everything in the forked child, in the only order that works:
+    /// bounding set and user while still privileged, then ambient
+    /// capabilities, then `no_new_privs`. Async-signal-safe; returns the
+    /// failed step and its errno.
+    pub(super) fn apply(&self) -> Result<(), (ExecStage, Errno)> {
+        let check = |ok: bool, stage: ExecStage| {
+            if ok {
+                Ok(())
+            } else {
+                Err((stage, Errno::last()))
+            }
+        };
+        // SAFETY: prctl with documented arguments on the calling process.
+        let prctl = |option: libc::c_int, arg: libc::c_ulong| unsafe {
+            libc::prctl(option, arg, 0 as libc::c_ulong, 0 as libc::c_ulong, 0) == 0
+        };
+        if self.keep != 0 && self.user.is_some() {
+            check(prctl(libc::PR_SET_KEEPCAPS, 1), ExecStage::KeepCaps)?;
+        }
+        for cap in bits(self.drop) {
+            check(
+                prctl(libc::PR_CAPBSET_DROP, cap.into()),
+                ExecStage::DropCaps,
+            )?;
+        }
+        if let Some(user) = &self.user {
+            check(drop_privileges(user), ExecStage::SwitchUser)?;
+        }
+        if self.keep != 0 {
+            check(raise_ambient(self.keep), ExecStage::AmbientCaps)?;
+        }
+        if self.no_new_privs {
+            check(prctl(libc::PR_SET_NO_NEW_PRIVS, 1), ExecStage::NoNewPrivs)?;
+        }
+        Ok(())
+    }
+}
+
+/// Capabilities the running kernel knows, so `ALL` never names one it rejects.
+fn known_capabilities() -> CapMask {
+    let last = fs::read_to_string("/proc/sys/kernel/cap_last_cap")
+        .ok()
+        .and_then(|raw| raw.trim().parse::<u32>().ok())
+        .unwrap_or(CAPABILITY_NAMES.len() as u32 - 1)
+        .min(63);
+    CapMask::MAX >> (63 - last)
+}
+
+fn bits(mask: CapMask) -> impl Iterator<Item = u32> {
+    (0..64).filter(move |cap| mask & (1 << cap) != 0)
+}
+
+/// Adds the permitted members of `keep` to the inheritable set, then to the
+/// ambient set so they survive exec for unprivileged users.
+fn raise_ambient(keep: CapMask) -> bool {
+    let mut header = CapHeader {
+        version: LINUX_CAPABILITY_VERSION_3,
+        pid: 0,
+    };
+    let mut data = [CapData::default(); 2];
+    // SAFETY: version 3 capget/capset read and write exactly two `CapData`.
+    unsafe {
+        if libc::syscall(libc::SYS_capget, &mut header, data.as_mut_ptr()) != 0 {
+            return false;
+        }
+        let permitted = CapMask::from(data[0].permitted) | CapMask::from(data[1].permitted) << 32;
+        let raise = keep & permitted;
+        data[0].inheritable |= raise as u32;
+        data[1].inheritable |= (raise >> 32) as u32;
+        if libc::syscall(libc::SYS_capset, &mut header, data.as_ptr()) != 0 {
+            return false;
+        }
+        bits(raise).all(|cap| {
+            libc::prctl(
+                libc::PR_CAP_AMBIENT,
+                libc::PR_CAP_AMBIENT_RAISE as libc::c_ulong,
+                cap as libc::c_ulong,
+                0 as libc::c_ulong,
+                0 as libc::c_ulong,
+            ) == 0
+        })
+    }
+}
+
+/// Logs the capability sets of a freshly exec'd child. Permitted and
+/// effective are left out: exec commits them after closing the exec-status
+/// pipe, so they may still show the pre-exec values here.
+pub(super) fn log_capabilities(process: &str, pid: Pid) {
+    let Ok(status) = fs::read_to_string(format!("/proc/{pid}/status")) else {
+        return;
+    };
+    let field = |key: &str| {
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+            .map(str::trim)
+    };
+    let set = |key: &str| {
+        field(key)
+            .and_then(|hex| CapMask::from_str_radix(hex, 16).ok())
+            .map(format_mask)
+    };
+    debug!(
+        process,
+        pid = pid.as_raw(),
+        bounding = set("CapBnd").as_deref(),
+        inheritable = set("CapInh").as_deref(),
+        ambient = set("CapAmb").as_deref(),
+        no_new_privs = field("NoNewPrivs") == Some("1"),
+        "child capabilities"
+    );
+}
+
+fn format_mask(mask: CapMask) -> String {
+    let all = known_capabilities();
+    if mask == 0 {
+        return "none".into();
+    }
+    if mask & all == all {
+        return ALL_CAPABILITIES.into();
+    }
+    bits(mask)
+        .map(|cap| match CAPABILITY_NAMES.get(cap as usize) {
+            Some(name) => name.to_string(),
+            None => cap.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn kept_capabilities_are_never_dropped() {
+        let cli = Cli::try_parse_from([
+            "tino",
+            "--drop-caps",
+            "ALL",
+            "--keep-caps",
+            "NET_BIND_SERVICE",
+            "--",
+            "true",
+        ])
+        .unwrap();
+        let privileges = Privileges::from_cli(&cli).unwrap();
+        assert_eq!(privileges.keep, 1 << 10);
+        assert_eq!(privileges.drop, known_capabilities() & !(1 << 10));
+        assert!(privileges.restricts());
+        assert!(!Privileges::default().restricts());
+    }
+
+    #[test]
+    fn masks_are_listed_by_name() {
+        assert_eq!(format_mask(0), "none");
+        assert_eq!(format_mask(1 << 10 | 1), "CHOWN,NET_BIND_SERVICE");
+        assert_eq!(format_mask(known_capabilities()), "ALL");
+    }
+}
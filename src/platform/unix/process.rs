@@ -0,0 +1,432 @@
+use crate::cli::KillScope;
+use anyhow::{Context, Result};
+use nix::{sys::signal::SigSet, unistd::Pid};
+use std::{
+    ffi::CString,
+    os::fd::{AsFd, BorrowedFd, OwnedFd, RawFd},
+    time::{Duration, Instant},
+};
+use tracing::info;
+
+use super::child::{ExecFailure, manage_process_group, prepare_command, spawn_child};
+use super::privileges::Privileges;
+use super::proctree::{cmdline, descendants};
+use super::signals::{Sig, pidfd_open, pidfd_send_signal, send_signal};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Termination {
+    Exited(i32),
+    Signaled { signal: Sig, core_dumped: bool },
+}
+
+impl Termination {
+    pub(super) fn code(self) -> i32 {
+        match self {
+            Termination::Exited(code) => code,
+            Termination::Signaled { signal, .. } => 128 + signal.as_raw(),
+        }
+    }
+
+    pub(super) fn signal(self) -> Option<Sig> {
+        match self {
+            Termination::Exited(_) => None,
+            Termination::Signaled { signal, .. } => Some(signal),
+        }
+    }
+}
+
+pub(super) struct Process {
+    pub name: String,
+    cmd: Vec<String>,
+    program: CString,
+    argv: Vec<CString>,
+    pub pid: Option<Pid>,
+    /// Held from spawn until reaped, so the PID cannot be recycled under us.
+    pidfd: Option<OwnedFd>,
+    pub running: bool,
+    pub use_pgroup: bool,
+    pub last_exit: Option<Termination>,
+    pub started_at: Option<Instant>,
+    pub restart_at: Option<Instant>,
+    /// Restarts since the process last stayed up for the reset window.
+    pub consecutive_restarts: u32,
+    pub restart_count: u32,
+    /// Restart on the next exit regardless of policy (control socket `restart`).
+    pub restart_requested: bool,
+    /// Escalate to SIGKILL if the process is still running at this point.
+    pub kill_at: Option<Instant>,
+}
+
+impl Process {
+    pub(super) fn new(name: &str, cmd: &[String]) -> Result<Self> {
+        let (program, argv) =
+            prepare_command(cmd).with_context(|| format!("prepare command {cmd:?}"))?;
+        Ok(Self {
+            name: name.to_string(),
+            cmd: cmd.to_vec(),
+            program,
+            argv,
+            pid: None,
+            pidfd: None,
+            running: false,
+            use_pgroup: false,
+            last_exit: None,
+            started_at: None,
+            restart_at: None,
+            consecutive_restarts: 0,
+            restart_count: 0,
+            restart_requested: false,
+            kill_at: None,
+        })
+    }
+
+    pub(super) fn spawn(
+        &mut self,
+        block: SigSet,
+        pgroup_kill: bool,
+        stdio: Option<[RawFd; 2]>,
+        privileges: &Privileges,
+    ) -> Result<Pid> {
+        let pid = match spawn_child(block, &self.program, &self.argv, stdio, privileges) {
+            Ok(pid) => pid,
+            Err(err) => {
+                if let Some(failure) = err.downcast_ref::<ExecFailure>() {
+                    self.mark_exec_failed(failure.exit_code());
+                }
+                return Err(err.context(format!("spawn child {:?}", self.cmd)));
+            }
+        };
+        self.pid = Some(pid);
+        self.pidfd = pidfd_open(pid);
+        self.running = true;
+        self.started_at = Some(Instant::now());
+        self.restart_at = None;
+        self.use_pgroup = manage_process_group(pgroup_kill, pid);
+        Ok(pid)
+    }
+
+    /// Running, or waiting for a scheduled restart.
+    pub(super) fn is_active(&self) -> bool {
+        self.running || self.restart_at.is_some()
+    }
+
+    /// Schedules a restart with exponential backoff and returns the delay, or
+    /// `None` once `max_restarts` consecutive restarts have been used up.
+    pub(super) fn schedule_restart(
+        &mut self,
+        initial: Duration,
+        max_delay: Duration,
+        reset_after: Duration,
+        max_restarts: Option<u32>,
+    ) -> Option<Duration> {
+        if self
+            .started_at
+            .is_some_and(|started| started.elapsed() >= reset_after)
+        {
+            self.consecutive_restarts = 0;
+        }
+        if max_restarts.is_some_and(|max| self.consecutive_restarts >= max) {
+            return None;
+        }
+        let factor = 1u32
+            .checked_shl(self.consecutive_restarts)
+            .unwrap_or(u32::MAX);
+        let delay = initial.saturating_mul(factor).min(max_delay);
+        self.consecutive_restarts += 1;
+        self.restart_count += 1;
+        self.restart_at = Some(Instant::now() + delay);
+        Some(delay)
+    }
+
+    /// Signals the process, or its process group when one is managed. Exited
+    /// processes are only reachable through their (possibly still populated) group.
+    pub(super) fn signal(&self, sig: Sig) {
+        if self.use_pgroup {
+            if let Some(pid) = self.pid {
+                send_signal(true, pid, sig);
+            }
+        } else {
+            self.signal_pid(sig);
+        }
+    }
+
+    /// Signals the process alone, through its pidfd when there is one.
+    fn signal_pid(&self, sig: Sig) {
+        let Some(pid) = self.pid.filter(|_| self.running) else {
+            return;
+        };
+        match &self.pidfd {
+            Some(pidfd) => pidfd_send_signal(pidfd.as_fd(), pid, sig),
+            None => send_signal(false, pid, sig),
+        }
+    }
+
+    /// Readable once the running process exits.
+    pub(super) fn exit_fd(&self) -> Option<BorrowedFd<'_>> {
+        self.pidfd.as_ref().map(AsFd::as_fd)
+    }
+
+    /// Marks the process reaped and releases its pidfd.
+    pub(super) fn mark_exited(&mut self, termination: Termination) {
+        self.running = false;
+        self.pidfd = None;
+        self.kill_at = None;
+        self.last_exit = Some(termination);
+    }
+
+    /// Records a failed `execvp` as an immediate exit, so restart policies
+    /// and backoff treat it like any other crash.
+    fn mark_exec_failed(&mut self, code: i32) {
+        self.pid = None;
+        self.started_at = Some(Instant::now());
+        self.restart_at = None;
+        self.mark_exited(Termination::Exited(code));
+    }
+
+    /// Like [`Process::signal`], but reaching exactly as far as `scope`.
+    pub(super) fn signal_scoped(&self, sig: Sig, scope: KillScope) {
+        match scope {
+            KillScope::Pid => self.signal_pid(sig),
+            KillScope::Group => self.signal(sig),
+            KillScope::Descendants | KillScope::All => {
+                // Collect first: once the parent dies its children are reparented.
+                let below = match self.pid {
+                    Some(pid) if self.running => descendants(pid),
+                    _ => Vec::new(),
+                };
+                self.signal(sig);
+                for pid in below {
+                    send_signal(false, pid, sig);
+                }
+            }
+        }
+    }
+}
+
+/// Supervised processes; index 0 is always the main command.
+pub(super) struct ProcessTable {
+    processes: Vec<Process>,
+}
+
+impl ProcessTable {
+    pub(super) fn new(main: Process) -> Self {
+        Self {
+            processes: vec![main],
+        }
+    }
+
+    pub(super) fn push(&mut self, process: Process) {
+        self.processes.push(process);
+    }
+
+    pub(super) fn main(&self) -> &Process {
+        &self.processes[0]
+    }
+
+    pub(super) fn get(&self, idx: usize) -> &Process {
+        &self.processes[idx]
+    }
+
+    pub(super) fn iter(&self) -> impl Iterator<Item = &Process> {
+        self.processes.iter()
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.processes.len()
+    }
+
+    pub(super) fn any_active(&self) -> bool {
+        self.processes.iter().any(Process::is_active)
+    }
+
+    pub(super) fn position(&self, name: &str) -> Option<usize> {
+        self.processes.iter().position(|p| p.name == name)
+    }
+
+    pub(super) fn next_restart(&self) -> Option<Instant> {
+        self.processes.iter().filter_map(|p| p.restart_at).min()
+    }
+
+    pub(super) fn next_kill(&self) -> Option<Instant> {
+        self.processes.iter().filter_map(|p| p.kill_at).min()
+    }
+
+    /// Sends SIGKILL to processes whose `kill_at` deadline has passed.
+    pub(super) fn kill_overdue(&mut self, now: Instant) {
+        for process in &mut self.processes {
+            if process.kill_at.is_some_and(|at| at <= now) {
+                process.kill_at = None;
+                info!(
+                    process = %process.name,
+                    pid = process.pid.map_or(0, Pid::as_raw),
+                    signal = %Sig::KILL,
+                    "still running after grace period; sending SIGKILL"
+                );
+                process.signal(Sig::KILL);
+            }
+        }
+    }
+
+    /// Indices of processes with a restart scheduled no later than `by`
+    /// (or at any time when `by` is `None`).
+    pub(super) fn pending_restarts(&self, by: Option<Instant>) -> Vec<usize> {
+        (0..self.processes.len())
+            .filter(|&idx| {
+                self.processes[idx]
+                    .restart_at
+                    .is_some_and(|at| by.is_none_or(|by| at <= by))
+            })
+            .collect()
+    }
+
+    pub(super) fn get_mut(&mut self, idx: usize) -> &mut Process {
+        &mut self.processes[idx]
+    }
+
+    /// Pidfds of running processes, readable when they exit.
+    pub(super) fn exit_fds(&self) -> Vec<BorrowedFd<'_>> {
+        self.processes.iter().filter_map(Process::exit_fd).collect()
+    }
+
+    pub(super) fn signal_all(&self, sig: Sig) {
+        for process in &self.processes {
+            process.signal(sig);
+        }
+    }
+
+    pub(super) fn signal_all_scoped(&self, sig: Sig, scope: KillScope) {
+        if scope == KillScope::All {
+            let below = descendants(Pid::this());
+            self.signal_all(sig);
+            signal_stragglers(&below, sig);
+            return;
+        }
+        for process in &self.processes {
+            process.signal_scoped(sig, scope);
+        }
+    }
+
+    /// Marks the running process with `pid` as exited and returns its index.
+    pub(super) fn record_exit(&mut self, pid: Pid, termination: Termination) -> Option<usize> {
+        let idx = self
+            .processes
+            .iter()
+            .position(|p| p.running && p.pid == Some(pid))?;
+        self.processes[idx].mark_exited(termination);
+        Some(idx)
+    }
+}
+
+/// Logs and signals each of `pids`, descendants of tino found in `/proc`.
+pub(super) fn signal_stragglers(pids: &[Pid], sig: Sig) {
+    for &pid in pids {
+        info!(
+            pid = pid.as_raw(),
+            cmdline = %cmdline(pid),
+            signal = %sig,
+            phase = "shutdown",
+            "signalling straggler"
+        );
+        send_signal(false, pid, sig);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn running_process(name: &str, pid: i32) -> Process {
+        let mut process = Process::new(name, &["/bin/true".to_string()]).unwrap();
+        process.pid = Some(Pid::from_raw(pid));
+        process.running = true;
+        process
+    }
+
+    #[test]
+    fn termination_code_follows_shell_convention() {
+        assert_eq!(Termination::Exited(3).code(), 3);
+        let killed = Termination::Signaled {
+            signal: Sig::KILL,
+            core_dumped: false,
+        };
+        assert_eq!(killed.code(), 137);
+    }
+
+    #[test]
+    fn restart_backoff_doubles_and_respects_limits() {
+        let mut process = running_process("main", 100);
+        let initial = Duration::from_millis(100);
+        let max_delay = Duration::from_millis(250);
+        let reset = Duration::from_secs(60);
+
+        let delays: Vec<_> = (0..4)
+            .map(|_| process.schedule_restart(initial, max_delay, reset, Some(3)))
+            .collect();
+        assert_eq!(
+            delays,
+            vec![
+                Some(Duration::from_millis(100)),
+                Some(Duration::from_millis(200)),
+                Some(Duration::from_millis(250)),
+                None,
+            ]
+        );
+        assert_eq!(process.restart_count, 3);
+        assert!(process.is_active());
+
+        process.started_at = Some(Instant::now() - reset);
+        assert_eq!(
+            process.schedule_restart(initial, max_delay, reset, Some(3)),
+            Some(initial)
+        );
+    }
+
+    #[test]
+    fn pidfd_is_held_until_reaped() {
+        let _lock = super::super::child_test_lock();
+        let sleeper = Process::new("main", &["sleep".to_string(), "5".to_string()]).unwrap();
+        let mut table = ProcessTable::new(sleeper);
+        let pid = table
+            .get_mut(0)
+            .spawn(SigSet::empty(), false, None, &Privileges::default())
+            .unwrap();
+        assert_eq!(table.exit_fds().len(), 1);
+
+        table.get(0).signal(Sig::KILL);
+        let mut status = 0;
+        // SAFETY: `status` is a valid out-pointer for the duration of the call.
+        assert_eq!(
+            unsafe { libc::waitpid(pid.as_raw(), &mut status, 0) },
+            pid.as_raw()
+        );
+        assert!(libc::WIFSIGNALED(status));
+        let killed = Termination::Signaled {
+            signal: Sig::KILL,
+            core_dumped: false,
+        };
+        assert_eq!(table.record_exit(pid, killed), Some(0));
+        assert!(table.exit_fds().is_empty());
+    }
+
+    #[test]
+    fn record_exit_only_matches_running_processes() {
+        let mut table = ProcessTable::new(running_process("main", 100));
+        table.push(running_process("logs", 200));
+
+        assert_eq!(
+            table.record_exit(Pid::from_raw(200), Termination::Exited(1)),
+            Some(1)
+        );
+        assert!(!table.get(1).running);
+        assert_eq!(table.get(1).last_exit, Some(Termination::Exited(1)));
+        assert_eq!(
+            table.record_exit(Pid::from_raw(200), Termination::Exited(1)),
+            None
+        );
+        assert!(table.any_active());
+        assert_eq!(
+            table.record_exit(Pid::from_raw(300), Termination::Exited(0)),
+            None
+        );
+    }
+}
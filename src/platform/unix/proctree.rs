@@ -0,0 +1,95 @@
+use nix::unistd::Pid;
+use std::fs;
+
+/// Every live descendant of `root` found through `/proc`, parents before
+/// their children.
+pub(super) fn descendants(root: Pid) -> Vec<Pid> {
+    let links = parent_links();
+    let mut found = Vec::new();
+    let mut frontier = vec![root];
+    while let Some(parent) = frontier.pop() {
+        for &(pid, ppid) in &links {
+            if ppid == parent && !found.contains(&pid) {
+                found.push(pid);
+                frontier.push(pid);
+            }
+        }
+    }
+    found
+}
+
+/// The command line of `pid` for logs, or its `[comm]` name for kernel
+/// threads and processes whose arguments are gone.
+pub(super) fn cmdline(pid: Pid) -> String {
+    let args = fs::read(format!("/proc/{pid}/cmdline")).unwrap_or_default();
+    let joined = args
+        .split(|&b| b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(String::from_utf8_lossy)
+        .collect::<Vec<_>>()
+        .join(" ");
+    if !joined.is_empty() {
+        return joined;
+    }
+    match fs::read_to_string(format!("/proc/{pid}/comm")) {
+        Ok(comm) => format!("[{}]", comm.trim_end()),
+        Err(_) => String::from("?"),
+    }
+}
+
+/// `(pid, parent pid)` for every live process visible in `/proc`; zombies
+/// are left to `waitpid`.
+fn parent_links() -> Vec<(Pid, Pid)> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<i32>().ok())
+        .filter_map(|pid| {
+            // Processes may exit while we look; skip them.
+            let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+            let (state, ppid) = parse_stat(&stat)?;
+            (state != 'Z').then_some((Pid::from_raw(pid), ppid))
+        })
+        .collect()
+}
+
+/// Fields 3 and 4 (state and parent) of `/proc/<pid>/stat`. The command name
+/// before them may itself contain spaces and parentheses, so parsing starts
+/// after the last `)`.
+fn parse_stat(stat: &str) -> Option<(char, Pid)> {
+    let mut fields = stat[stat.rfind(')')? + 1..].split_whitespace();
+    let state = fields.next()?.chars().next()?;
+    let ppid = fields.next()?.parse().ok()?;
+    Some((state, Pid::from_raw(ppid)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_stat_after_tricky_command_names() {
+        assert_eq!(
+            parse_stat("42 (a) b (c)) S 7 42 42 0"),
+            Some(('S', Pid::from_raw(7)))
+        );
+        assert_eq!(parse_stat("garbage"), None);
+    }
+
+    #[test]
+    fn finds_spawned_children() {
+        let _lock = super::super::child_test_lock();
+        let mut child = std::process::Command::new("sleep")
+            .arg("5")
+            .spawn()
+            .unwrap();
+        let pid = Pid::from_raw(child.id() as i32);
+        let found = descendants(Pid::this());
+        let name = cmdline(pid);
+        let _ = child.kill();
+        let _ = child.wait();
+        assert!(found.contains(&pid), "{found:?}");
+        assert_eq!(name, "sleep 5");
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+/// Size limit and number of rotated generations for `--stdout-file`/`--stderr-file`.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Rotation {
+    pub max_size: Option<u64>,
+    pub keep: usize,
+}
+
+/// An append-only file rotated to `PATH.1` .. `PATH.N`.
+pub(super) struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    rotation: Rotation,
+}
+
+impl RotatingFile {
+    pub(super) fn open(path: &Path, rotation: Rotation) -> io::Result<Self> {
+        let file = open_append(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            size,
+            rotation,
+        })
+    }
+
+    pub(super) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends `data`, rotating first if it would push the file past the limit.
+    pub(super) fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        if let Some(max) = self.rotation.max_size
+            && self.size > 0
+            && self.size + data.len() as u64 > max
+        {
+            self.rotate()?;
+        }
+        self.file.write_all(data)?;
+        self.size += data.len() as u64;
+        Ok(())
+    }
+
+    /// Shifts `PATH.n` to `PATH.n+1`, moves the live file to `PATH.1` and
+    /// reopens it. With `keep == 0` the live file is truncated instead.
+    pub(super) fn rotate(&mut self) -> io::Result<()> {
+        if self.rotation.keep == 0 {
+            self.file.set_len(0)?;
+            self.size = 0;
+            return Ok(());
+        }
+        for n in (1..self.rotation.keep).rev() {
+            match fs::rename(self.generation(n), self.generation(n + 1)) {
+                Err(err) if err.kind() != ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+        fs::rename(&self.path, self.generation(1))?;
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn generation(&self, n: usize) -> PathBuf {
+        let mut name = OsString::from(self.path.as_os_str());
+        name.push(format!(".{n}"));
+        PathBuf::from(name)
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates_by_size_and_keeps_generations() {
+        let dir = std::env::temp_dir().join(format!("tino-rotate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.log");
+        let rotation = Rotation {
+            max_size: Some(8),
+            keep: 2,
+        };
+        let mut file = RotatingFile::open(&path, rotation).unwrap();
+        for line in ["one\n", "two\n", "three\n", "four\n", "five\n"] {
+            file.write(line.as_bytes()).unwrap();
+        }
+        let read = |suffix: &str| {
+            let mut name = path.clone().into_os_string();
+            name.push(suffix);
+            fs::read_to_string(name).unwrap_or_default()
+        };
+        assert_eq!(read(""), "five\n");
+        assert_eq!(read(".1"), "four\n");
+        assert_eq!(read(".2"), "three\n");
+        assert_eq!(read(".3"), "", "only `keep` generations are retained");
+
+        file.rotate().unwrap();
+        assert_eq!(read(""), "");
+        assert_eq!(read(".1"), "five\n");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,48 +1,225 @@
-use anyhow::{Context, Result};
+use crate::cli::{SignalRewrite, StopStage};
+use anyhow::{Context, Result, anyhow};
+use libc::c_int;
 use nix::{
     errno::Errno,
     sys::{
-        signal::{SIGCHLD, SigSet, Signal, kill, killpg},
-        signalfd::{SfdFlags, SigSet as NixSigSet, SignalFd},
+        signal::SigSet,
+        signalfd::{SfdFlags, SignalFd},
     },
     unistd::Pid,
 };
-use tracing::warn;
+use std::{
+    collections::HashMap,
+    fmt,
+    os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd},
+    time::Duration,
+};
+use tracing::{debug, warn};
+
+/// A raw signal number. Unlike `nix::sys::signal::Signal` this also covers
+/// the real-time range.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) struct Sig(c_int);
+
+impl Sig {
+    pub(super) const HUP: Sig = Sig(libc::SIGHUP);
+    pub(super) const INT: Sig = Sig(libc::SIGINT);
+    pub(super) const QUIT: Sig = Sig(libc::SIGQUIT);
+    pub(super) const KILL: Sig = Sig(libc::SIGKILL);
+    pub(super) const TERM: Sig = Sig(libc::SIGTERM);
+    pub(super) const CHLD: Sig = Sig(libc::SIGCHLD);
+
+    pub(super) fn from_raw(number: c_int) -> Self {
+        Sig(number)
+    }
+
+    pub(super) fn as_raw(self) -> c_int {
+        self.0
+    }
+}
+
+impl fmt::Display for Sig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match crate::signals::signal_name(self.0) {
+            Some(name) => f.write_str(&name),
+            None => write!(f, "signal {}", self.0),
+        }
+    }
+}
+
+impl fmt::Debug for Sig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Resolved `--rewrite` table applied to received signals before forwarding.
+#[derive(Default)]
+pub(super) struct SignalRewrites(HashMap<Sig, Option<Sig>>);
+
+impl SignalRewrites {
+    pub(super) fn resolve(rewrites: &[SignalRewrite]) -> Result<Self> {
+        let lookup = |name: &str| {
+            signal_by_name(name).ok_or_else(|| anyhow!("unsupported signal {name} in --rewrite"))
+        };
+        let mut table = HashMap::new();
+        for rewrite in rewrites {
+            let to = rewrite.to.as_deref().map(lookup).transpose()?;
+            table.insert(lookup(&rewrite.from)?, to);
+        }
+        Ok(Self(table))
+    }
+
+    /// Returns the signal to forward in place of `sig`, or `None` to drop it.
+    pub(super) fn apply(&self, sig: Sig) -> Option<Sig> {
+        self.0.get(&sig).copied().unwrap_or(Some(sig))
+    }
+}
+
+/// A resolved `--stop-sequence`/`--straggler-sequence` step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct Stage {
+    pub signal: Sig,
+    pub timeout: Duration,
+}
+
+/// SIGTERM then SIGKILL, each followed by `grace`.
+pub(super) fn default_stop_stages(grace: Duration) -> Vec<Stage> {
+    [Sig::TERM, Sig::KILL]
+        .into_iter()
+        .map(|signal| Stage {
+            signal,
+            timeout: grace,
+        })
+        .collect()
+}
 
-pub(super) fn setup_signal_delivery() -> Result<(SigSet, SignalFd)> {
-    let mut block = SigSet::empty();
-    block.add(SIGCHLD);
-    for &s in crate::signals::FORWARDED_SIGNALS.iter() {
-        block.add(s);
+/// Resolves a configured ladder, falling back to [`default_stop_stages`].
+pub(super) fn stop_stages(stages: &[StopStage], grace: Duration) -> Result<Vec<Stage>> {
+    if stages.is_empty() {
+        return Ok(default_stop_stages(grace));
     }
-    block.thread_block().context("sigprocmask")?;
+    stages
+        .iter()
+        .map(|stage| {
+            Ok(Stage {
+                signal: signal_by_name(&stage.signal).ok_or_else(|| {
+                    anyhow!("unsupported signal {} in stop sequence", stage.signal)
+                })?,
+                timeout: stage.timeout.unwrap_or(grace),
+            })
+        })
+        .collect()
+}
 
-    let mut sfd_set = NixSigSet::empty();
-    for &s in crate::signals::FORWARDED_SIGNALS
+/// Resolves `--forward`/`--no-forward` into the signals tino intercepts.
+pub(super) fn forwarded_signals(add: &[String], remove: &[String]) -> Result<Vec<Sig>> {
+    crate::signals::resolve_forwarded_names(add, remove)
         .iter()
-        .chain(std::iter::once(&SIGCHLD))
-    {
-        sfd_set.add(s);
+        .map(|name| signal_by_name(name).ok_or_else(|| anyhow!("unsupported signal {name}")))
+        .collect()
+}
+
+/// Builds a signal set through libc, since `SigSet::add` cannot express
+/// real-time signals.
+fn signal_set(signals: impl IntoIterator<Item = Sig>) -> Result<SigSet> {
+    let mut raw = *SigSet::empty().as_ref();
+    for sig in signals {
+        // SAFETY: `raw` is an initialised sigset_t owned by this frame.
+        if unsafe { libc::sigaddset(&mut raw, sig.0) } == -1 {
+            return Err(Errno::last()).with_context(|| format!("sigaddset {sig}"));
+        }
     }
-    let signal_fd = SignalFd::with_flags(&sfd_set, SfdFlags::SFD_NONBLOCK | SfdFlags::SFD_CLOEXEC)
+    // SAFETY: `raw` was initialised by `SigSet::empty` and only modified via `sigaddset`.
+    Ok(unsafe { SigSet::from_sigset_t_unchecked(raw) })
+}
+
+pub(super) fn setup_signal_delivery(forwarded: &[Sig]) -> Result<(SigSet, SignalFd)> {
+    let mask = signal_set(forwarded.iter().copied().chain(std::iter::once(Sig::CHLD)))?;
+    mask.thread_block().context("sigprocmask")?;
+
+    let signal_fd = SignalFd::with_flags(&mask, SfdFlags::SFD_NONBLOCK | SfdFlags::SFD_CLOEXEC)
         .context("signalfd")?;
 
-    Ok((block, signal_fd))
+    Ok((mask, signal_fd))
 }
 
-pub(super) fn signal_by_name(name: &str) -> Option<Signal> {
-    crate::signals::signal_from_str(name)
+/// Terminates tino with `sig` so the parent observes a signaled wait status.
+/// Core dumps are suppressed unless the child produced one. Returns only if
+/// the signal did not kill tino.
+pub(super) fn reraise(sig: Sig, core_dumped: bool) {
+    if !core_dumped {
+        let mut limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        // SAFETY: `limit` is a valid rlimit for both calls.
+        unsafe {
+            if libc::getrlimit(libc::RLIMIT_CORE, &mut limit) == 0 {
+                limit.rlim_cur = 0;
+                libc::setrlimit(libc::RLIMIT_CORE, &limit);
+            }
+        }
+    }
+    // SAFETY: resetting to the default disposition and raising a signal are
+    // sound; the signal stays pending until it is unblocked below.
+    unsafe {
+        libc::signal(sig.0, libc::SIG_DFL);
+        libc::raise(sig.0);
+    }
+    if let Ok(mask) = signal_set([sig]) {
+        let _ = mask.thread_unblock();
+    }
+}
+
+pub(super) fn signal_by_name(name: &str) -> Option<Sig> {
+    crate::signals::signal_number(name).map(Sig)
+}
+
+/// Opens a pidfd for our own unreaped child `pid`, or returns `None` where
+/// the kernel lacks pidfd support (before 5.3). Pidfds are close-on-exec.
+pub(super) fn pidfd_open(pid: Pid) -> Option<OwnedFd> {
+    // SAFETY: `pidfd_open` takes a PID and flags and returns a new descriptor.
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid.as_raw(), 0) };
+    if fd < 0 {
+        debug!(pid = pid.as_raw(), error = %Errno::last(), "pidfd_open failed; signalling by PID");
+        return None;
+    }
+    // SAFETY: the descriptor was just created and nothing else owns it.
+    Some(unsafe { OwnedFd::from_raw_fd(fd as RawFd) })
+}
+
+/// Signals the process behind `pidfd`, which cannot have been recycled.
+pub(super) fn pidfd_send_signal(pidfd: BorrowedFd<'_>, pid: Pid, sig: Sig) {
+    // SAFETY: a null `siginfo` asks the kernel to fill it in as `kill` would.
+    let res = Errno::result(unsafe {
+        libc::syscall(
+            libc::SYS_pidfd_send_signal,
+            pidfd.as_raw_fd(),
+            sig.0,
+            std::ptr::null::<libc::siginfo_t>(),
+            0,
+        )
+    });
+    if let Err(e) = res
+        && e != Errno::ESRCH
+    {
+        warn!(pid = pid.as_raw(), signal = %sig, error = %e, "signal delivery failed");
+    }
 }
 
-pub(super) fn send_signal(pgid: bool, child: Pid, sig: Signal) {
-    let res = if pgid {
-        killpg(Pid::from_raw(child.as_raw()), sig)
+pub(super) fn send_signal(pgid: bool, child: Pid, sig: Sig) {
+    let target = if pgid {
+        -child.as_raw()
     } else {
-        kill(child, sig)
+        child.as_raw()
     };
+    // SAFETY: `kill` has no memory-safety preconditions.
+    let res = Errno::result(unsafe { libc::kill(target, sig.0) });
     if let Err(e) = res
         && e != Errno::ESRCH
     {
-        warn!("forward {:?} failed: {}", sig, e);
+        warn!(pid = child.as_raw(), signal = %sig, error = %e, "signal delivery failed");
     }
 }
@@ -0,0 +1,198 @@
+use anyhow::{Result, anyhow, bail};
+use std::{env, ffi::CString, fs, os::unix::ffi::OsStrExt};
+
+const PASSWD: &str = "/etc/passwd";
+const GROUP: &str = "/etc/group";
+
+/// Identity a child switches to for `--user`, resolved before fork so the
+/// child only makes async-signal-safe syscalls.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct Credentials {
+    pub uid: libc::uid_t,
+    pub gid: libc::gid_t,
+    /// Supplementary groups, including the primary group.
+    pub groups: Vec<libc::gid_t>,
+    pub name: String,
+    pub home: String,
+}
+
+struct PasswdEntry {
+    name: String,
+    uid: libc::uid_t,
+    gid: libc::gid_t,
+    home: String,
+}
+
+struct GroupEntry {
+    name: String,
+    gid: libc::gid_t,
+    members: Vec<String>,
+}
+
+impl Credentials {
+    /// Resolves `user[:group]` (names or numeric IDs) against the system
+    /// account databases.
+    pub(super) fn resolve(spec: &str) -> Result<Self> {
+        let passwd = fs::read_to_string(PASSWD).unwrap_or_default();
+        let group = fs::read_to_string(GROUP).unwrap_or_default();
+        Self::resolve_from(spec, &passwd, &group).map_err(|e| anyhow!("--user {spec}: {e}"))
+    }
+
+    fn resolve_from(spec: &str, passwd: &str, group: &str) -> Result<Self> {
+        let (user, group_spec) = match spec.split_once(':') {
+            Some((user, group)) => (user, Some(group)),
+            None => (spec, None),
+        };
+        if user.is_empty() || group_spec.is_some_and(str::is_empty) {
+            bail!("expected USER[:GROUP]");
+        }
+        let users = parse_passwd(passwd);
+        let groups = parse_group(group);
+
+        let numeric = user.parse::<libc::uid_t>().ok();
+        let entry = users
+            .into_iter()
+            .find(|e| numeric.map_or(e.name == user, |uid| e.uid == uid));
+        let (uid, primary, name, home) = match (entry, numeric) {
+            (Some(e), _) => (e.uid, e.gid, e.name, e.home),
+            // Unknown numeric users behave like Docker's: root group, `/` as home.
+            (None, Some(uid)) => (uid, 0, uid.to_string(), "/".to_string()),
+            (None, None) => bail!("unknown user '{user}' in {PASSWD}"),
+        };
+        let gid = match group_spec {
+            None => primary,
+            Some(spec) => match spec.parse::<libc::gid_t>() {
+                Ok(gid) => gid,
+                Err(_) => groups
+                    .iter()
+                    .find(|g| g.name == spec)
+                    .map(|g| g.gid)
+                    .ok_or_else(|| anyhow!("unknown group '{spec}' in {GROUP}"))?,
+            },
+        };
+        let mut supplementary = vec![gid];
+        for g in &groups {
+            if g.members.contains(&name) && !supplementary.contains(&g.gid) {
+                supplementary.push(g.gid);
+            }
+        }
+        Ok(Self {
+            uid,
+            gid,
+            groups: supplementary,
+            name,
+            home,
+        })
+    }
+
+    /// tino's environment with `HOME` and `USER` pointing at the new identity.
+    pub(super) fn environment(&self) -> Result<Vec<CString>> {
+        let mut vars = vec![
+            CString::new(format!("HOME={}", self.home))?,
+            CString::new(format!("USER={}", self.name))?,
+        ];
+        for (key, value) in env::vars_os() {
+            if key == "HOME" || key == "USER" {
+                continue;
+            }
+            let mut var = key.as_bytes().to_vec();
+            var.push(b'=');
+            var.extend_from_slice(value.as_bytes());
+            vars.push(CString::new(var)?);
+        }
+        Ok(vars)
+    }
+}
+
+fn parse_passwd(contents: &str) -> Vec<PasswdEntry> {
+    contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            let [name, _, uid, gid, _, home, ..] = fields[..] else {
+                return None;
+            };
+            Some(PasswdEntry {
+                name: name.to_string(),
+                uid: uid.parse().ok()?,
+                gid: gid.parse().ok()?,
+                home: home.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn parse_group(contents: &str) -> Vec<GroupEntry> {
+    contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            let [name, _, gid, members] = fields[..] else {
+                return None;
+            };
+            Some(GroupEntry {
+                name: name.to_string(),
+                gid: gid.parse().ok()?,
+                members: members
+                    .split(',')
+                    .filter(|m| !m.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// Switches the forked child to `credentials`. Async-signal-safe; returns
+/// `false` if any step fails.
+pub(super) fn drop_privileges(credentials: &Credentials) -> bool {
+    // SAFETY: plain syscalls on the calling process; `groups` outlives the call.
+    unsafe {
+        libc::setgroups(credentials.groups.len(), credentials.groups.as_ptr()) == 0
+            && libc::setgid(credentials.gid) == 0
+            && libc::setuid(credentials.uid) == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSWD_FILE: &str = "root:x:0:0:root:/root:/bin/sh\n\
+                               # comment\n\
+                               app:x:1000:1000:App:/home/app:/bin/sh\n";
+    const GROUP_FILE: &str = "root:x:0:\n\
+                              app:x:1000:\n\
+                              web:x:33:app,other\n\
+                              audio:x:29:other\n";
+
+    #[test]
+    fn resolves_names_and_supplementary_groups() {
+        let creds = Credentials::resolve_from("app", PASSWD_FILE, GROUP_FILE).unwrap();
+        assert_eq!(
+            creds,
+            Credentials {
+                uid: 1000,
+                gid: 1000,
+                groups: vec![1000, 33],
+                name: "app".into(),
+                home: "/home/app".into(),
+            }
+        );
+        let by_id = Credentials::resolve_from("1000:web", PASSWD_FILE, GROUP_FILE).unwrap();
+        assert_eq!((by_id.uid, by_id.gid), (1000, 33));
+        assert_eq!(by_id.groups, vec![33]);
+    }
+
+    #[test]
+    fn unknown_numeric_users_get_defaults_but_names_must_exist() {
+        let creds = Credentials::resolve_from("4242:7", PASSWD_FILE, GROUP_FILE).unwrap();
+        assert_eq!((creds.uid, creds.gid), (4242, 7));
+        assert_eq!((creds.name.as_str(), creds.home.as_str()), ("4242", "/"));
+        assert!(Credentials::resolve_from("nobody-here", PASSWD_FILE, GROUP_FILE).is_err());
+        assert!(Credentials::resolve_from("app:nogroup", PASSWD_FILE, GROUP_FILE).is_err());
+        assert!(Credentials::resolve_from("app:", PASSWD_FILE, GROUP_FILE).is_err());
+    }
+}
@@ -1,7 +1,5 @@
 #[cfg(target_os = "linux")]
-use nix::sys::signal::Signal;
-#[cfg(target_os = "linux")]
-use once_cell::sync::Lazy;
+use libc::c_int;
 
 macro_rules! signal_spec {
     ($macro:ident) => {
@@ -21,10 +19,22 @@ macro_rules! signal_spec {
             (PIPE, SIGPIPE),
             (ALRM, SIGALRM),
             (TERM, SIGTERM),
+            (STKFLT, SIGSTKFLT),
+            (CHLD, SIGCHLD),
             (CONT, SIGCONT),
-            (WINCH, SIGWINCH),
+            (STOP, SIGSTOP),
+            (TSTP, SIGTSTP),
             (TTIN, SIGTTIN),
             (TTOU, SIGTTOU),
+            (URG, SIGURG),
+            (XCPU, SIGXCPU),
+            (XFSZ, SIGXFSZ),
+            (VTALRM, SIGVTALRM),
+            (PROF, SIGPROF),
+            (WINCH, SIGWINCH),
+            (IO, SIGIO),
+            (PWR, SIGPWR),
+            (SYS, SIGSYS),
         ]
     };
 }
@@ -35,55 +45,177 @@ macro_rules! generate_name_array {
     };
 }
 
-const SIGNAL_NAMES_ARRAY: [&str; 19] = signal_spec!(generate_name_array);
+const SIGNAL_NAMES_ARRAY: [&str; 31] = signal_spec!(generate_name_array);
 
 pub(crate) const SIGNAL_NAMES: &[&str] = &SIGNAL_NAMES_ARRAY;
 
-pub(crate) fn canonical_signal_name(raw: &str) -> Option<&'static str> {
+/// Alternative names accepted on input and mapped to their canonical form.
+const SIGNAL_ALIASES: &[(&str, &str)] = &[("IOT", "ABRT"), ("POLL", "IO"), ("CLD", "CHLD")];
+
+/// Normalises a signal given as a name (with or without `SIG`, any case), an
+/// alias, `RTMIN+n`/`RTMAX-n`, or a number into its `SIG`-prefixed canonical
+/// name. Real-time signals are canonicalised the way `kill -l` prints them.
+pub(crate) fn canonical_signal_name(raw: &str) -> Option<String> {
     let trimmed = raw.trim();
     if trimmed.is_empty() {
         return None;
     }
     let upper = trimmed.to_ascii_uppercase();
     let candidate = upper.strip_prefix("SIG").unwrap_or(&upper);
-    SIGNAL_NAMES.iter().copied().find(|name| *name == candidate)
+    let candidate = SIGNAL_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == candidate)
+        .map_or(candidate, |(_, name)| name);
+    if let Some(name) = SIGNAL_NAMES.iter().find(|name| **name == candidate) {
+        return Some(format!("SIG{name}"));
+    }
+    numeric_signal_name(candidate)
 }
 
 #[cfg(target_os = "linux")]
-macro_rules! generate_signal_array {
+fn numeric_signal_name(candidate: &str) -> Option<String> {
+    let number = if candidate.bytes().all(|b| b.is_ascii_digit()) {
+        candidate.parse().ok()?
+    } else {
+        realtime_number(candidate)?
+    };
+    signal_name(number)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn numeric_signal_name(_candidate: &str) -> Option<String> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+macro_rules! generate_number_array {
     ($(($name:ident, $sig:ident)),+ $(,)?) => {
-        [$(Signal::$sig),+]
+        [$(libc::$sig),+]
+    };
+}
+
+#[cfg(target_os = "linux")]
+const SIGNAL_NUMBERS_ARRAY: [c_int; 31] = signal_spec!(generate_number_array);
+
+/// Resolves `RTMIN`, `RTMIN+n`, `RTMAX` and `RTMAX-n` (without `SIG`).
+#[cfg(target_os = "linux")]
+fn realtime_number(name: &str) -> Option<c_int> {
+    let (min, max) = (libc::SIGRTMIN(), libc::SIGRTMAX());
+    let offset = |raw: &str| raw.parse::<c_int>().ok().filter(|n| *n >= 0);
+    let number = if let Some(rest) = name.strip_prefix("RTMIN") {
+        match rest.strip_prefix('+') {
+            Some(n) => min.checked_add(offset(n)?)?,
+            None if rest.is_empty() => min,
+            None => return None,
+        }
+    } else if let Some(rest) = name.strip_prefix("RTMAX") {
+        match rest.strip_prefix('-') {
+            Some(n) => max.checked_sub(offset(n)?)?,
+            None if rest.is_empty() => max,
+            None => return None,
+        }
+    } else {
+        return None;
+    };
+    (min..=max).contains(&number).then_some(number)
+}
+
+/// Canonical name for a signal number, or `None` if Linux has no such signal.
+#[cfg(target_os = "linux")]
+pub(crate) fn signal_name(number: c_int) -> Option<String> {
+    if let Some(idx) = SIGNAL_NUMBERS_ARRAY.iter().position(|n| *n == number) {
+        return Some(format!("SIG{}", SIGNAL_NAMES[idx]));
+    }
+    let (min, max) = (libc::SIGRTMIN(), libc::SIGRTMAX());
+    if !(min..=max).contains(&number) {
+        return None;
+    }
+    let name = if number == min {
+        "SIGRTMIN".to_string()
+    } else if number == max {
+        "SIGRTMAX".to_string()
+    } else if number - min <= (max - min) / 2 {
+        format!("SIGRTMIN+{}", number - min)
+    } else {
+        format!("SIGRTMAX-{}", max - number)
     };
+    Some(name)
 }
 
+/// Signal number for any spelling accepted by [`canonical_signal_name`].
 #[cfg(target_os = "linux")]
-const SIGNAL_VALUES_ARRAY: [Signal; 19] = signal_spec!(generate_signal_array);
+pub(crate) fn signal_number(raw: &str) -> Option<c_int> {
+    let canonical = canonical_signal_name(raw)?;
+    let name = canonical.strip_prefix("SIG").unwrap_or(&canonical);
+    match SIGNAL_NAMES.iter().position(|candidate| *candidate == name) {
+        Some(idx) => Some(SIGNAL_NUMBERS_ARRAY[idx]),
+        None => realtime_number(name),
+    }
+}
 
 #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
 pub(crate) const FORWARDED_SIGNAL_NAMES: &[&str] = &[
     "HUP", "INT", "QUIT", "TERM", "USR1", "USR2", "WINCH", "CONT", "TTIN", "TTOU",
 ];
 
-#[cfg(target_os = "linux")]
-pub(crate) static FORWARDED_SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
-    FORWARDED_SIGNAL_NAMES
-        .iter()
-        .map(|name| {
-            signal_from_canonical(name)
-                .unwrap_or_else(|| panic!("missing canonical signal mapping for {name}"))
-        })
-        .collect()
-});
-
-#[cfg(target_os = "linux")]
-pub(crate) fn signal_from_canonical(name: &str) -> Option<Signal> {
-    SIGNAL_NAMES
+/// The default forwarded set adjusted by `--forward`/`--no-forward`, as
+/// `SIG`-prefixed canonical names.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn resolve_forwarded_names(add: &[String], remove: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = FORWARDED_SIGNAL_NAMES
         .iter()
-        .position(|candidate| *candidate == name)
-        .map(|idx| SIGNAL_VALUES_ARRAY[idx])
+        .map(|name| format!("SIG{name}"))
+        .collect();
+    for name in add {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+    names.retain(|name| !remove.contains(name));
+    names
 }
 
-#[cfg(target_os = "linux")]
-pub(crate) fn signal_from_str(raw: &str) -> Option<Signal> {
-    canonical_signal_name(raw).and_then(signal_from_canonical)
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_aliases_and_numbers_are_canonicalised() {
+        assert_eq!(canonical_signal_name("sys").as_deref(), Some("SIGSYS"));
+        assert_eq!(canonical_signal_name("SIGPWR").as_deref(), Some("SIGPWR"));
+        assert_eq!(canonical_signal_name("iot").as_deref(), Some("SIGABRT"));
+        assert_eq!(canonical_signal_name("15").as_deref(), Some("SIGTERM"));
+        assert_eq!(canonical_signal_name("SIG9").as_deref(), Some("SIGKILL"));
+        assert_eq!(canonical_signal_name("+3"), None);
+        assert_eq!(canonical_signal_name("0"), None);
+        assert_eq!(canonical_signal_name("NOPE"), None);
+    }
+
+    #[test]
+    fn realtime_signals_round_trip() {
+        let (min, max) = (libc::SIGRTMIN(), libc::SIGRTMAX());
+        assert_eq!(signal_number("RTMIN"), Some(min));
+        assert_eq!(signal_number("sigrtmin+3"), Some(min + 3));
+        assert_eq!(signal_number("RTMAX-1"), Some(max - 1));
+        assert_eq!(signal_number("RTMAX"), Some(max));
+        assert_eq!(signal_number(&(min + 2).to_string()), Some(min + 2));
+        assert_eq!(signal_number("RTMIN+999"), None);
+        assert_eq!(signal_number("RTMAX+1"), None);
+        assert_eq!(signal_number("RTMIN-1"), None);
+
+        for number in min..=max {
+            let name = signal_name(number).unwrap();
+            assert_eq!(signal_number(&name), Some(number), "{name}");
+        }
+        assert_eq!(signal_name(min + 3).as_deref(), Some("SIGRTMIN+3"));
+        assert_eq!(signal_name(max - 2).as_deref(), Some("SIGRTMAX-2"));
+    }
+
+    #[test]
+    fn classic_numbers_match_the_table() {
+        for (name, number) in SIGNAL_NAMES.iter().zip(SIGNAL_NUMBERS_ARRAY) {
+            assert_eq!(signal_number(name), Some(number));
+            assert_eq!(signal_name(number), Some(format!("SIG{name}")));
+        }
+    }
 }
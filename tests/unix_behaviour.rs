@@ -50,6 +50,194 @@ fn remap_exit_zeroes_expected_codes() {
     );
 }
 
+#[test]
+fn exec_failures_exit_like_a_shell() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!("tino-exec-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create exec test dir");
+    let not_executable = dir.join("plain.sh");
+    std::fs::write(&not_executable, "echo hi\n").expect("write plain script");
+    let bad_interpreter = dir.join("bad.sh");
+    std::fs::write(&bad_interpreter, "#!/no/such/interpreter\necho hi\n")
+        .expect("write shebang script");
+    std::fs::set_permissions(&bad_interpreter, std::fs::Permissions::from_mode(0o755))
+        .expect("chmod shebang script");
+
+    let run = |program: &str| {
+        let output = Command::new(tino_bin())
+            .args(["--", program])
+            .output()
+            .expect("failed to run tino exec failure test");
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        )
+    };
+    let cases = [
+        ("/no/such/binary".to_string(), 127, "no such file"),
+        ("tino-no-such-command".to_string(), 127, "not found in PATH"),
+        (
+            not_executable.display().to_string(),
+            126,
+            "permission denied",
+        ),
+        (
+            bad_interpreter.display().to_string(),
+            126,
+            "bad interpreter /no/such/interpreter",
+        ),
+    ];
+    for (program, code, reason) in cases {
+        let (status, stderr) = run(&program);
+        assert_eq!(status, Some(code), "{program}: {stderr}");
+        assert!(stderr.contains(reason), "{program}: {stderr}");
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn user_option_drops_privileges_before_exec() {
+    // Switching users needs root; unprivileged test runs have nothing to check.
+    // SAFETY: geteuid has no preconditions.
+    if unsafe { libc::geteuid() } != 0 {
+        return;
+    }
+    let output = Command::new(tino_bin())
+        .env("HOME", "/root")
+        .args([
+            "--user",
+            "65534:65534",
+            "--",
+            "sh",
+            "-c",
+            "echo $(id -u) $(id -g) $(id -G | tr ' ' ,) $HOME",
+        ])
+        .output()
+        .expect("failed to run tino --user");
+
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let fields: Vec<&str> = stdout.split_whitespace().collect();
+    assert_eq!(&fields[..3], ["65534", "65534", "65534"], "{stdout}");
+    assert_ne!(
+        fields[3], "/root",
+        "HOME must follow the new user: {stdout}"
+    );
+}
+
+#[test]
+fn privilege_setup_failures_are_reported_like_exec_failures() {
+    // Root runs go through an outer tino that switches to an unprivileged user
+    // first; it execs a copy of tino that user can reach.
+    let dir = std::env::temp_dir().join(format!("tino-privileges-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create privilege test dir");
+    let tino = dir.join("tino");
+    std::fs::copy(tino_bin(), &tino).expect("copy tino for privilege test");
+    let unprivileged = |args: &[&str]| {
+        let mut command = Command::new(&tino);
+        // SAFETY: geteuid has no preconditions.
+        if unsafe { libc::geteuid() } == 0 {
+            command.args(["--user", "65534", "--"]).arg(&tino);
+        }
+        let output = command
+            .args(args)
+            .output()
+            .expect("failed to run tino privilege failure test");
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        )
+    };
+
+    let (status, stderr) = unprivileged(&["--user", "0", "--", "true"]);
+    assert_eq!(status, Some(126), "{stderr}");
+    assert!(
+        stderr.contains("switching user failed: operation not permitted"),
+        "{stderr}"
+    );
+
+    let (status, stderr) = unprivileged(&["--drop-caps", "ALL", "--", "true"]);
+    assert_eq!(status, Some(126), "{stderr}");
+    assert!(
+        stderr.contains("dropping bounding capabilities failed: operation not permitted"),
+        "{stderr}"
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn capability_options_restrict_the_child() {
+    // Dropping bounding capabilities needs CAP_SETPCAP; only root runs check it.
+    // SAFETY: geteuid has no preconditions.
+    if unsafe { libc::geteuid() } != 0 {
+        return;
+    }
+    let output = Command::new(tino_bin())
+        .args([
+            "--user",
+            "65534",
+            "--no-new-privs",
+            "--drop-caps",
+            "ALL",
+            "--keep-caps",
+            "NET_BIND_SERVICE",
+            "--",
+            "grep",
+            "-E",
+            "^(CapEff|CapBnd|CapAmb|NoNewPrivs):",
+            "/proc/self/status",
+        ])
+        .output()
+        .expect("failed to run tino capability test");
+
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let fields: Vec<&str> = stdout
+        .lines()
+        .map(|line| line.split_whitespace().last().unwrap_or(""))
+        .collect();
+    // Only CAP_NET_BIND_SERVICE (bit 10) survives, and it stays usable for the non-root user.
+    assert_eq!(
+        fields,
+        [
+            "0000000000000400",
+            "0000000000000400",
+            "0000000000000400",
+            "1"
+        ],
+        "{stdout}"
+    );
+}
+
+#[test]
+fn map_exit_applies_ranges_signals_and_fallback() {
+    let run = |script: &str| {
+        Command::new(tino_bin())
+            .args([
+                "--map-exit",
+                "1-9=1",
+                "--map-exit",
+                "*=2",
+                "--map-exit",
+                "TERM=0",
+                "--",
+                "sh",
+                "-c",
+                script,
+            ])
+            .status()
+            .expect("failed to run tino map-exit test")
+            .code()
+    };
+
+    assert_eq!(run("exit 0"), Some(0));
+    assert_eq!(run("exit 7"), Some(1));
+    assert_eq!(run("exit 42"), Some(2));
+    assert_eq!(run("kill -TERM $$"), Some(0));
+    assert_eq!(run("kill -KILL $$"), Some(2));
+}
+
 #[test]
 fn signal_forwarding_reaches_child() {
     use nix::{
@@ -141,3 +329,907 @@ fn pgroup_kill_escalates_after_grace() {
         "expected escalation to SIGKILL reflected in exit code"
     );
 }
+
+#[test]
+fn sidecars_are_terminated_when_main_exits() {
+    use std::time::{Duration, Instant};
+    let started = Instant::now();
+    let status = Command::new(tino_bin())
+        .args(["--sidecar", "idle=sleep 30", "--", "sh", "-c", "exit 3"])
+        .status()
+        .expect("failed to run tino sidecar test");
+
+    assert_eq!(status.code(), Some(3), "expected main exit code to win");
+    assert!(
+        started.elapsed() < Duration::from_secs(10),
+        "sidecar kept tino alive"
+    );
+}
+
+#[test]
+fn exit_policy_any_follows_first_exit() {
+    let status = Command::new(tino_bin())
+        .args([
+            "--exit-policy",
+            "any",
+            "--sidecar",
+            "crash=sh -c 'exit 5'",
+            "--",
+            "sleep",
+            "30",
+        ])
+        .status()
+        .expect("failed to run tino exit policy test");
+
+    assert_eq!(
+        status.code(),
+        Some(5),
+        "expected sidecar exit code to end supervision"
+    );
+}
+
+#[test]
+fn restart_on_failure_stops_after_max_restarts() {
+    let marker = std::env::temp_dir().join(format!("tino-restart-{}", std::process::id()));
+    let _ = std::fs::remove_file(&marker);
+    let script = format!("echo run >> '{}'; exit 4", marker.display());
+    let status = Command::new(tino_bin())
+        .args([
+            "--restart",
+            "on-failure",
+            "--max-restarts",
+            "2",
+            "--restart-delay-ms",
+            "10",
+            "--",
+            "sh",
+            "-c",
+            &script,
+        ])
+        .status()
+        .expect("failed to run tino restart test");
+
+    let runs = std::fs::read_to_string(&marker).unwrap_or_default();
+    let _ = std::fs::remove_file(&marker);
+    assert_eq!(
+        status.code(),
+        Some(4),
+        "expected final failure to propagate"
+    );
+    assert_eq!(
+        runs.lines().count(),
+        3,
+        "expected initial run plus 2 restarts"
+    );
+}
+
+#[test]
+fn restart_on_failure_treats_remapped_codes_as_success() {
+    let status = Command::new(tino_bin())
+        .args([
+            "--restart",
+            "on-failure",
+            "-e",
+            "3",
+            "--",
+            "sh",
+            "-c",
+            "exit 3",
+        ])
+        .status()
+        .expect("failed to run tino remapped restart test");
+
+    assert!(status.success(), "remapped exit code should not restart");
+}
+
+#[test]
+fn config_file_supplies_command_and_remap() {
+    let path = std::env::temp_dir().join(format!("tino-config-{}.toml", std::process::id()));
+    std::fs::write(
+        &path,
+        "command = \"sh -c 'exit 3'\"\nremap-exit = [3]\ngrace-ms = 50\n",
+    )
+    .expect("write tino config");
+
+    let status = Command::new(tino_bin())
+        .arg("--config")
+        .arg(&path)
+        .status()
+        .expect("failed to run tino config test");
+    let _ = std::fs::remove_file(&path);
+
+    assert!(
+        status.success(),
+        "expected config remap to zero exit code, got {:?}",
+        status.code()
+    );
+}
+
+#[test]
+fn invalid_config_is_reported() {
+    let output = Command::new(tino_bin())
+        .args(["--config", "/nonexistent/tino.toml", "--", "/bin/true"])
+        .output()
+        .expect("failed to run tino invalid config test");
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("/nonexistent/tino.toml"),
+        "expected config path in error\n{stderr}"
+    );
+}
+
+#[test]
+fn control_socket_reports_status_and_stops() {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+
+    let socket = std::env::temp_dir().join(format!("tino-control-{}.sock", std::process::id()));
+    // A socket file nobody listens on is stale and gets replaced.
+    drop(std::os::unix::net::UnixListener::bind(&socket).expect("bind stale control socket"));
+    let mut child = Command::new(tino_bin())
+        .stdout(Stdio::piped())
+        .arg("--control-socket")
+        .arg(&socket)
+        .args(["--", "sh", "-c", "printf 'ready\\n'; exec sleep 30"])
+        .spawn()
+        .expect("failed to spawn tino control test");
+
+    let mut stdout = BufReader::new(child.stdout.take().expect("control test stdout"));
+    let mut ready = String::new();
+    stdout
+        .read_line(&mut ready)
+        .expect("read readiness marker for control test");
+    assert_eq!(ready.trim_end(), "ready", "unexpected readiness marker");
+
+    let request = |line: &str| {
+        let mut stream = UnixStream::connect(&socket).expect("connect control socket");
+        stream
+            .write_all(format!("{line}\n").as_bytes())
+            .expect("write control request");
+        let mut reply = String::new();
+        stream
+            .read_to_string(&mut reply)
+            .expect("read control reply");
+        reply
+    };
+
+    let second = Command::new(tino_bin())
+        .arg("--control-socket")
+        .arg(&socket)
+        .args(["--", "true"])
+        .output()
+        .expect("failed to run second tino on the control socket");
+    assert_eq!(
+        second.status.code(),
+        Some(1),
+        "live socket must not be taken"
+    );
+    let stderr = String::from_utf8_lossy(&second.stderr);
+    assert!(stderr.contains("control socket in use"), "{stderr}");
+
+    let status = request("status");
+    assert!(status.contains(r#""ok":true"#), "status failed: {status}");
+    assert!(
+        status.contains(r#""name":"main""#),
+        "missing main: {status}"
+    );
+    assert!(
+        status.contains(r#""running":true"#),
+        "main not running: {status}"
+    );
+
+    let ctl = Command::new(tino_bin())
+        .arg("ctl")
+        .arg("--socket")
+        .arg(&socket)
+        .arg("status")
+        .output()
+        .expect("failed to run tino ctl status");
+    assert!(ctl.status.success(), "tino ctl status failed");
+    let table = String::from_utf8_lossy(&ctl.stdout);
+    assert!(
+        table
+            .lines()
+            .any(|line| line.starts_with("main ") && line.contains("running")),
+        "unexpected ctl status output\n{table}"
+    );
+
+    let ctl = Command::new(tino_bin())
+        .env("TINO_CONTROL_SOCKET", &socket)
+        .args(["ctl", "--json", "restart", "nope"])
+        .output()
+        .expect("failed to run tino ctl restart");
+    assert_eq!(ctl.status.code(), Some(1), "unknown process should fail");
+    let json = String::from_utf8_lossy(&ctl.stdout);
+    assert!(
+        json.contains(r#""ok":false"#),
+        "unexpected ctl json\n{json}"
+    );
+
+    let stop = request("stop --grace 2000");
+    assert!(stop.contains(r#""ok":true"#), "stop failed: {stop}");
+
+    let exit = child.wait().expect("failed to wait on tino control test");
+    assert_eq!(exit.code(), Some(143), "expected SIGTERM exit from stop");
+    assert!(!socket.exists(), "control socket should be removed on exit");
+}
+
+#[test]
+fn rewrite_translates_forwarded_signal() {
+    use nix::{
+        sys::signal::{Signal, kill},
+        unistd::Pid,
+    };
+    let mut child = Command::new(tino_bin())
+        .stdout(Stdio::piped())
+        .args([
+            "--rewrite",
+            "TERM:USR1",
+            "--",
+            "sh",
+            "-c",
+            "trap 'exit 42' USR1; trap 'exit 1' TERM; printf 'ready\\n'; while true; do sleep 1; done",
+        ])
+        .spawn()
+        .expect("failed to spawn tino rewrite test");
+
+    let mut stdout = BufReader::new(child.stdout.take().expect("rewrite test stdout"));
+    let mut ready = String::new();
+    stdout
+        .read_line(&mut ready)
+        .expect("read readiness marker for rewrite test");
+    assert_eq!(ready.trim_end(), "ready", "unexpected readiness marker");
+    drop(stdout);
+    kill(Pid::from_raw(child.id() as i32), Signal::SIGTERM).expect("failed to send SIGTERM");
+
+    let status = child.wait().expect("failed to wait on tino rewrite test");
+    assert_eq!(
+        status.code(),
+        Some(42),
+        "expected SIGTERM to reach the child as SIGUSR1"
+    );
+}
+
+#[test]
+fn forward_adds_signals_to_forwarded_set() {
+    use nix::{
+        sys::signal::{Signal, kill},
+        unistd::Pid,
+    };
+    let mut child = Command::new(tino_bin())
+        .stdout(Stdio::piped())
+        .args([
+            "--forward",
+            "ALRM",
+            "--",
+            "sh",
+            "-c",
+            "trap 'exit 42' ALRM; printf 'ready\\n'; while true; do sleep 1; done",
+        ])
+        .spawn()
+        .expect("failed to spawn tino forward test");
+
+    let mut stdout = BufReader::new(child.stdout.take().expect("forward test stdout"));
+    let mut ready = String::new();
+    stdout
+        .read_line(&mut ready)
+        .expect("read readiness marker for forward test");
+    assert_eq!(ready.trim_end(), "ready", "unexpected readiness marker");
+    drop(stdout);
+    kill(Pid::from_raw(child.id() as i32), Signal::SIGALRM).expect("failed to send SIGALRM");
+
+    let status = child.wait().expect("failed to wait on tino forward test");
+    assert_eq!(status.code(), Some(42), "expected SIGALRM to be forwarded");
+}
+
+#[test]
+fn realtime_signals_are_forwarded_and_reported() {
+    let rtmin3 = libc::SIGRTMIN() + 3;
+    let mut child = Command::new(tino_bin())
+        .stdout(Stdio::piped())
+        .args([
+            "--forward",
+            "RTMIN+3",
+            "--",
+            "sh",
+            "-c",
+            &format!("trap 'exit 42' {rtmin3}; printf 'ready\\n'; while true; do sleep 1; done"),
+        ])
+        .spawn()
+        .expect("failed to spawn tino realtime test");
+
+    let mut stdout = BufReader::new(child.stdout.take().expect("realtime test stdout"));
+    let mut ready = String::new();
+    stdout
+        .read_line(&mut ready)
+        .expect("read readiness marker for realtime test");
+    assert_eq!(ready.trim_end(), "ready", "unexpected readiness marker");
+    drop(stdout);
+    // SAFETY: sending a signal to the spawned tino process.
+    assert_eq!(unsafe { libc::kill(child.id() as i32, rtmin3) }, 0);
+
+    let status = child.wait().expect("failed to wait on tino realtime test");
+    assert_eq!(
+        status.code(),
+        Some(42),
+        "expected SIGRTMIN+3 to be forwarded"
+    );
+
+    let status = Command::new(tino_bin())
+        .args(["--", "sh", "-c", &format!("kill -{rtmin3} $$")])
+        .status()
+        .expect("failed to run tino with realtime-signalled child");
+    assert_eq!(status.code(), Some(128 + rtmin3));
+}
+
+#[test]
+fn reraise_reproduces_child_signal_death() {
+    use std::os::unix::process::ExitStatusExt;
+
+    let status = Command::new(tino_bin())
+        .args(["--reraise", "--", "sh", "-c", "kill -USR1 $$"])
+        .status()
+        .expect("failed to run tino reraise test");
+    assert_eq!(status.signal(), Some(libc::SIGUSR1), "{status:?}");
+    assert!(!status.core_dumped());
+
+    let status = Command::new(tino_bin())
+        .args([
+            "--reraise",
+            "--map-exit",
+            "USR1=3",
+            "--",
+            "sh",
+            "-c",
+            "kill -USR1 $$",
+        ])
+        .status()
+        .expect("failed to run tino reraise remap test");
+    assert_eq!(status.code(), Some(3), "remapped deaths exit normally");
+}
+
+#[test]
+fn reraise_removes_readiness_file_first() {
+    use std::os::unix::process::ExitStatusExt;
+
+    let ready = std::env::temp_dir().join(format!("tino-reraise-ready-{}", std::process::id()));
+    std::fs::write(&ready, "").expect("create readiness file");
+    let status = Command::new(tino_bin())
+        .arg("--reraise")
+        .arg("--readiness-file")
+        .arg(&ready)
+        .args(["--", "sh", "-c", "kill -USR1 $$"])
+        .status()
+        .expect("failed to run tino reraise readiness test");
+    assert_eq!(status.signal(), Some(libc::SIGUSR1), "{status:?}");
+    assert!(!ready.exists(), "readiness file outlived the re-raise");
+}
+
+#[test]
+fn json_log_format_emits_structured_events() {
+    let output = Command::new(tino_bin())
+        .args(["--log-format", "json", "--", "sh", "-c", "exit 4"])
+        .output()
+        .expect("failed to run tino json log test");
+    assert_eq!(output.status.code(), Some(4));
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let events: Vec<serde_json::Value> = stderr
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("log line is not JSON"))
+        .collect();
+    let exit = events
+        .iter()
+        .find(|event| event["message"] == "exiting")
+        .expect("missing exit event");
+    assert_eq!(exit["exit_code"], 4);
+    assert_eq!(exit["phase"], "exit");
+    assert_eq!(exit["level"], "INFO");
+}
+
+#[test]
+fn log_file_separates_tino_logs_from_child_output() {
+    let dir = std::env::temp_dir().join(format!("tino-log-file-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create log dir");
+    let log = dir.join("tino.log");
+
+    let output = Command::new(tino_bin())
+        .args(["--log-timestamps", "rfc3339", "--log-file"])
+        .arg(&log)
+        .args(["--", "sh", "-c", "echo child-err >&2"])
+        .output()
+        .expect("failed to run tino log-file test");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "child-err\n");
+
+    let logged = std::fs::read_to_string(&log).expect("read tino log file");
+    let _ = std::fs::remove_dir_all(&dir);
+    let line = logged.lines().last().expect("tino log file is empty");
+    assert!(line.contains("exiting"), "{logged}");
+    assert!(
+        line.starts_with(|c: char| c.is_ascii_digit()) && line.contains('T'),
+        "expected an RFC 3339 timestamp: {line}"
+    );
+}
+
+#[test]
+fn output_capture_prefixes_and_wraps_lines() {
+    let output = Command::new(tino_bin())
+        .args([
+            "--output",
+            "prefixed",
+            "--sidecar",
+            "side=sh -c 'echo from-side'",
+            "--",
+            "sh",
+            "-c",
+            "echo hello; echo oops >&2; printf partial; sleep 0.2",
+        ])
+        .output()
+        .expect("failed to run tino prefixed output test");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines: Vec<_> = stdout.lines().collect();
+    lines.sort_unstable();
+    assert_eq!(
+        lines,
+        ["[main] hello", "[main] partial", "[side] from-side"]
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("[main] oops\n"));
+
+    let output = Command::new(tino_bin())
+        .args(["--output", "json", "--", "sh", "-c", "echo a >&2"])
+        .output()
+        .expect("failed to run tino json output test");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let record: serde_json::Value = serde_json::from_str(
+        stderr
+            .lines()
+            .find(|line| line.contains(r#""stream""#))
+            .expect("missing output record"),
+    )
+    .unwrap();
+    assert_eq!(record["process"], "main");
+    assert_eq!(record["stream"], "stderr");
+    assert_eq!(record["line"], "a");
+}
+
+#[test]
+fn output_is_relayed_while_sidecars_stop() {
+    use std::time::{Duration, Instant};
+    // More than a pipe buffer written on SIGTERM must not block the sidecar.
+    let started = Instant::now();
+    let output = Command::new(tino_bin())
+        .args([
+            "--output",
+            "plain",
+            "-t",
+            "5000",
+            "--sidecar",
+            "talk=sh -c 'trap \"yes | head -c 200000; exit 0\" TERM; while :; do sleep 0.1; done'",
+            "--",
+            "sleep",
+            "0.5",
+        ])
+        .output()
+        .expect("failed to run tino shutdown output test");
+    assert!(output.status.success(), "{output:?}");
+    assert!(
+        started.elapsed() < Duration::from_secs(4),
+        "sidecar was left blocked on its output"
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().filter(|line| *line == "y").count(), 100_000);
+}
+
+#[test]
+fn output_files_rotate_by_size_and_on_sighup() {
+    use nix::{
+        sys::signal::{Signal, kill},
+        unistd::Pid,
+    };
+    let dir = std::env::temp_dir().join(format!("tino-output-file-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create output dir");
+    let path = dir.join("app.log");
+
+    let mut child = Command::new(tino_bin())
+        .stderr(Stdio::piped())
+        .args(["--output-max-size", "8", "--output-keep", "2", "--forward", "HUP"])
+        .arg("--stdout-file")
+        .arg(&path)
+        .args([
+            "--",
+            "sh",
+            "-c",
+            "trap 'echo after-hup; exit 0' HUP; echo one; echo two; echo three; echo ready >&2; while true; do sleep 0.1; done",
+        ])
+        .spawn()
+        .expect("failed to spawn tino output-file test");
+
+    let mut stderr = BufReader::new(child.stderr.take().expect("output-file test stderr"));
+    let mut ready = String::new();
+    stderr
+        .read_line(&mut ready)
+        .expect("read readiness marker for output-file test");
+    assert_eq!(ready.trim_end(), "ready", "unexpected readiness marker");
+    kill(Pid::from_raw(child.id() as i32), Signal::SIGHUP).expect("failed to send SIGHUP");
+    let status = child
+        .wait()
+        .expect("failed to wait on tino output-file test");
+    assert!(status.success());
+
+    let read = |suffix: &str| {
+        let mut name = path.clone().into_os_string();
+        name.push(suffix);
+        std::fs::read_to_string(name).unwrap_or_default()
+    };
+    let files = [read(""), read(".1"), read(".2"), read(".3")];
+    assert_eq!(files, ["after-hup\n", "three\n", "one\ntwo\n", ""]);
+
+    // Without `--forward HUP`, rotation consumes the signal.
+    std::fs::remove_dir_all(&dir).expect("clear output dir");
+    std::fs::create_dir_all(&dir).expect("create output dir");
+    let mut child = Command::new(tino_bin())
+        .stderr(Stdio::piped())
+        .arg("--stdout-file")
+        .arg(&path)
+        .args([
+            "--",
+            "sh",
+            "-c",
+            "trap 'echo after-hup' HUP; echo one; echo ready >&2; sleep 1; echo two",
+        ])
+        .spawn()
+        .expect("failed to spawn tino output-file test");
+    let mut stderr = BufReader::new(child.stderr.take().expect("output-file test stderr"));
+    ready.clear();
+    stderr
+        .read_line(&mut ready)
+        .expect("read readiness marker for output-file test");
+    assert_eq!(ready.trim_end(), "ready", "unexpected readiness marker");
+    kill(Pid::from_raw(child.id() as i32), Signal::SIGHUP).expect("failed to send SIGHUP");
+    let status = child
+        .wait()
+        .expect("failed to wait on tino output-file test");
+    assert!(status.success());
+    let files = [read(""), read(".1")];
+    let _ = std::fs::remove_dir_all(&dir);
+    assert_eq!(files, ["two\n", "one\n"]);
+}
+
+#[test]
+fn stop_sequence_escalates_through_each_stage() {
+    use nix::{
+        sys::signal::{Signal, kill},
+        unistd::Pid,
+    };
+    let mut child = Command::new(tino_bin())
+        .stdout(Stdio::piped())
+        .args([
+            "--stop-sequence",
+            "TERM:300ms,INT:300ms,KILL",
+            "--",
+            "sh",
+            "-c",
+            "trap 'echo got-term' TERM; trap 'echo got-int' INT; echo ready; while true; do sleep 0.05; done",
+        ])
+        .spawn()
+        .expect("failed to spawn tino stop-sequence test");
+
+    let mut stdout = BufReader::new(child.stdout.take().expect("stop-sequence test stdout"));
+    let mut line = String::new();
+    stdout
+        .read_line(&mut line)
+        .expect("read readiness marker for stop-sequence test");
+    assert_eq!(line.trim_end(), "ready", "unexpected readiness marker");
+    kill(Pid::from_raw(child.id() as i32), Signal::SIGTERM).expect("failed to send SIGTERM");
+
+    let mut seen = Vec::new();
+    for _ in 0..2 {
+        line.clear();
+        stdout.read_line(&mut line).expect("read stop stage marker");
+        seen.push(line.trim_end().to_string());
+    }
+    let status = child
+        .wait()
+        .expect("failed to wait on tino stop-sequence test");
+    assert_eq!(seen, ["got-term", "got-int"]);
+    assert_eq!(
+        status.code(),
+        Some(137),
+        "expected the final KILL stage to end the child"
+    );
+}
+
+#[test]
+fn stop_signal_and_stop_command_replace_forwarding() {
+    use nix::{
+        sys::signal::{Signal, kill},
+        unistd::Pid,
+    };
+    let dir = std::env::temp_dir().join(format!("tino-stop-command-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create stop-command dir");
+    let flag = dir.join("stop");
+    let stop_command = format!("touch '{}'", flag.display());
+    let wait_for_flag = format!(
+        "trap '' TERM; echo ready; while [ ! -e '{}' ]; do sleep 0.05; done; exit 7",
+        flag.display()
+    );
+    let cases: [(&[&str], &str, i32); 2] = [
+        (
+            &["--stop-signal", "USR1"],
+            "trap 'exit 42' USR1; trap 'exit 1' TERM; echo ready; while true; do sleep 0.05; done",
+            42,
+        ),
+        (&["--stop-command", &stop_command], &wait_for_flag, 7),
+    ];
+    for (flags, script, expected) in cases {
+        let mut child = Command::new(tino_bin())
+            .stdout(Stdio::piped())
+            .args(flags)
+            .args(["--", "sh", "-c", script])
+            .spawn()
+            .expect("failed to spawn tino stop action test");
+        let mut stdout = BufReader::new(child.stdout.take().expect("stop action test stdout"));
+        let mut ready = String::new();
+        stdout
+            .read_line(&mut ready)
+            .expect("read readiness marker for stop action test");
+        assert_eq!(ready.trim_end(), "ready", "unexpected readiness marker");
+        kill(Pid::from_raw(child.id() as i32), Signal::SIGTERM).expect("failed to send SIGTERM");
+        let status = child
+            .wait()
+            .expect("failed to wait on tino stop action test");
+        assert_eq!(status.code(), Some(expected), "{flags:?}");
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn stop_delay_holds_back_termination_until_repeated() {
+    use nix::{
+        sys::signal::{Signal, kill},
+        unistd::Pid,
+    };
+    use std::time::{Duration, Instant};
+    let dir = std::env::temp_dir().join(format!("tino-stop-delay-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create stop-delay dir");
+    let readiness = dir.join("ready");
+
+    for (delay, repeat) in [("400ms", false), ("10s", true)] {
+        std::fs::write(&readiness, "").expect("create readiness file");
+        let mut child = Command::new(tino_bin())
+            .stdout(Stdio::piped())
+            .args(["--stop-delay", delay, "--readiness-file"])
+            .arg(&readiness)
+            .args([
+                "--",
+                "sh",
+                "-c",
+                "trap 'exit 3' TERM; echo ready; while true; do sleep 0.05; done",
+            ])
+            .spawn()
+            .expect("failed to spawn tino stop-delay test");
+        let mut stdout = BufReader::new(child.stdout.take().expect("stop-delay test stdout"));
+        let mut ready = String::new();
+        stdout
+            .read_line(&mut ready)
+            .expect("read readiness marker for stop-delay test");
+        assert_eq!(ready.trim_end(), "ready", "unexpected readiness marker");
+
+        let pid = Pid::from_raw(child.id() as i32);
+        let sent = Instant::now();
+        kill(pid, Signal::SIGTERM).expect("failed to send SIGTERM");
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(!readiness.exists(), "readiness file should go on SIGTERM");
+        if repeat {
+            kill(pid, Signal::SIGTERM).expect("failed to send second SIGTERM");
+        } else {
+            assert!(
+                child.try_wait().unwrap().is_none(),
+                "child stopped before the delay"
+            );
+        }
+        let status = child
+            .wait()
+            .expect("failed to wait on tino stop-delay test");
+        assert_eq!(status.code(), Some(3));
+        let elapsed = sent.elapsed();
+        if repeat {
+            assert!(
+                elapsed < Duration::from_secs(5),
+                "second SIGTERM skips the delay"
+            );
+        } else {
+            assert!(elapsed >= Duration::from_millis(400), "{elapsed:?}");
+        }
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn repeated_interrupts_force_sigkill() {
+    use nix::{
+        sys::signal::{Signal, kill},
+        unistd::Pid,
+    };
+    let mut child = Command::new(tino_bin())
+        .stdout(Stdio::piped())
+        .args([
+            "--interrupt-kill-count",
+            "3",
+            "--",
+            "sh",
+            "-c",
+            "trap 'echo got-int' INT; echo ready; while true; do sleep 0.05; done",
+        ])
+        .spawn()
+        .expect("failed to spawn tino interrupt test");
+    let mut stdout = BufReader::new(child.stdout.take().expect("interrupt test stdout"));
+    let mut line = String::new();
+    stdout
+        .read_line(&mut line)
+        .expect("read readiness marker for interrupt test");
+    assert_eq!(line.trim_end(), "ready", "unexpected readiness marker");
+
+    let pid = Pid::from_raw(child.id() as i32);
+    kill(pid, Signal::SIGINT).expect("failed to send SIGINT");
+    line.clear();
+    stdout
+        .read_line(&mut line)
+        .expect("read forwarded interrupt marker");
+    assert_eq!(line.trim_end(), "got-int", "first SIGINT is forwarded");
+    for _ in 0..2 {
+        // Pending signals coalesce, so give tino time to read each one.
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        kill(pid, Signal::SIGINT).expect("failed to send SIGINT");
+    }
+    let status = child.wait().expect("failed to wait on tino interrupt test");
+    assert_eq!(status.code(), Some(137), "third SIGINT forces SIGKILL");
+}
+
+#[test]
+fn dropped_interrupts_do_not_count_towards_sigkill() {
+    use nix::{
+        sys::signal::{Signal, kill},
+        unistd::Pid,
+    };
+    let mut child = Command::new(tino_bin())
+        .stdout(Stdio::piped())
+        .args(["--interrupt-kill-count", "3", "--rewrite", "INT:0"])
+        .args(["--", "sh", "-c", "echo ready; sleep 1; exit 5"])
+        .spawn()
+        .expect("failed to spawn tino dropped interrupt test");
+    let mut stdout = BufReader::new(child.stdout.take().expect("interrupt test stdout"));
+    let mut line = String::new();
+    stdout
+        .read_line(&mut line)
+        .expect("read readiness marker for dropped interrupt test");
+    assert_eq!(line.trim_end(), "ready", "unexpected readiness marker");
+
+    let pid = Pid::from_raw(child.id() as i32);
+    for _ in 0..3 {
+        kill(pid, Signal::SIGINT).expect("failed to send SIGINT");
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    let status = child.wait().expect("failed to wait on tino interrupt test");
+    assert_eq!(
+        status.code(),
+        Some(5),
+        "dropped SIGINTs must not force SIGKILL"
+    );
+}
+
+#[test]
+fn kill_scope_escalates_without_process_groups() {
+    use nix::{
+        sys::signal::{Signal, kill},
+        unistd::Pid,
+    };
+    use std::time::{Duration, Instant};
+    // Dead or a zombie waiting for its new parent to reap it.
+    let gone = |pid: Pid| {
+        std::fs::read_to_string(format!("/proc/{pid}/stat")).map_or(true, |stat| {
+            stat.rsplit(')')
+                .next()
+                .unwrap_or("")
+                .trim_start()
+                .starts_with('Z')
+        })
+    };
+
+    for (scope, grandchild_killed) in [("pid", false), ("descendants", true)] {
+        let mut child = Command::new(tino_bin())
+            .stdout(Stdio::piped())
+            .args(["-t", "200", "--kill-scope", scope])
+            .args(["--", "sh", "-c", "trap '' TERM; sleep 30 & echo $!; wait"])
+            .spawn()
+            .expect("failed to spawn tino kill-scope test");
+        let mut stdout = BufReader::new(child.stdout.take().expect("kill-scope test stdout"));
+        let mut line = String::new();
+        stdout
+            .read_line(&mut line)
+            .expect("read grandchild pid for kill-scope test");
+        let grandchild = Pid::from_raw(line.trim().parse().expect("grandchild pid"));
+
+        kill(Pid::from_raw(child.id() as i32), Signal::SIGTERM).expect("failed to send SIGTERM");
+        let status = child
+            .wait()
+            .expect("failed to wait on tino kill-scope test");
+        assert_eq!(
+            status.code(),
+            Some(137),
+            "{scope}: SIGKILL follows the grace period"
+        );
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while !gone(grandchild) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(gone(grandchild), grandchild_killed, "{scope}");
+        let _ = kill(grandchild, Signal::SIGKILL);
+    }
+}
+
+#[test]
+fn kill_scope_all_logs_escalated_descendants() {
+    use nix::{
+        sys::signal::{Signal, kill},
+        unistd::Pid,
+    };
+    let mut child = Command::new(tino_bin())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .args(["-t", "200", "--kill-scope", "all"])
+        .args([
+            "--",
+            "sh",
+            "-c",
+            "trap '' TERM; sleep 30 & echo started; wait",
+        ])
+        .spawn()
+        .expect("failed to spawn tino kill-scope all escalation test");
+    let mut stdout = BufReader::new(child.stdout.take().expect("escalation test stdout"));
+    let mut line = String::new();
+    stdout
+        .read_line(&mut line)
+        .expect("read readiness marker for escalation test");
+    assert_eq!(line.trim_end(), "started", "unexpected readiness marker");
+
+    kill(Pid::from_raw(child.id() as i32), Signal::SIGTERM).expect("failed to send SIGTERM");
+    let output = child
+        .wait_with_output()
+        .expect("failed to wait on tino escalation test");
+    assert_eq!(output.status.code(), Some(137), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let straggler = stderr
+        .lines()
+        .find(|line| line.contains("signalling straggler") && line.contains("sleep 30"))
+        .unwrap_or_else(|| panic!("missing straggler log: {stderr}"));
+    assert!(straggler.contains("SIGKILL"), "{straggler}");
+}
+
+#[test]
+fn kill_scope_all_sweeps_adopted_orphans() {
+    use std::time::{Duration, Instant};
+    let started = Instant::now();
+    let output = Command::new(tino_bin())
+        .args(["-s", "-t", "200", "--kill-scope", "all"])
+        .args(["--", "sh", "-c", "trap '' TERM; sleep 30 & echo started"])
+        .output()
+        .expect("failed to run tino kill-scope all test");
+    assert!(output.status.success());
+    assert!(
+        started.elapsed() < Duration::from_secs(10),
+        "orphan should be killed, not waited for"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let straggler = stderr
+        .lines()
+        .find(|line| line.contains("signalling straggler"))
+        .unwrap_or_else(|| panic!("missing straggler log: {stderr}"));
+    assert!(straggler.contains("sleep 30"), "{straggler}");
+    assert!(stderr.contains("SIGKILL"), "{stderr}");
+}